@@ -1,17 +1,24 @@
 use anyhow::{Context, Result};
-use chrono::Utc;
-use clap::{Parser, Subcommand, ValueEnum};
-use image::ImageReader;
-use image_hasher::{HashAlg, HasherConfig};
+use chrono::{DateTime, Local, NaiveDate, TimeZone, Utc};
+use clap::{ArgGroup, Parser, Subcommand, ValueEnum};
+use image::{ImageFormat, ImageReader};
+use image_hasher::{HashAlg, Hasher, HasherConfig};
 use indicatif::{ProgressBar, ProgressStyle};
 use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs::{self, File, OpenOptions};
-use std::io::{self, BufRead, BufReader, Read, Write};
+use std::io::{self, BufRead, BufReader, Cursor, Read, Seek, SeekFrom, Write};
+use std::panic::{self, AssertUnwindSafe};
 use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::{Duration, Instant, SystemTime};
 use walkdir::WalkDir;
 
+mod similarity_index;
+use similarity_index::SimilarityIndex;
+
 #[derive(Serialize, Deserialize, Debug)]
 struct CullHistoryRecord {
     timestamp: String,
@@ -21,11 +28,27 @@ struct CullHistoryRecord {
 }
 
 #[derive(Serialize, Deserialize, Debug)]
+#[serde(default)]
 struct Config {
     auto_confirm: bool,
     selection_strategy: SelectionStrategy,
     excluded_dirs: Vec<String>,
+    /// Glob patterns, matched against paths relative to the scanned directory,
+    /// for files and directories to leave out (e.g. "**/*.psd")
+    excluded_globs: Vec<String>,
+    /// Only scan these image types, by extension (empty = all supported types)
+    file_types: Vec<String>,
+    /// Where culled files are moved: relative to the scanned directory, or absolute
+    duplicates_dir: PathBuf,
     duplicates_hash_threshold: u32,
+    /// Per-format threshold overrides, keyed by canonical extension (e.g. "png")
+    duplicates_format_thresholds: BTreeMap<String, u32>,
+    /// Skip files larger than this many bytes
+    scan_max_file_size: Option<u64>,
+    /// Skip images whose width or height is below this many pixels
+    scan_min_dimension: Option<u32>,
+    /// Ask before hashing more than this many images (0 = no limit)
+    scan_soft_limit: u64,
 }
 
 impl Default for Config {
@@ -34,7 +57,14 @@ impl Default for Config {
             auto_confirm: false,
             selection_strategy: SelectionStrategy::Oldest,
             excluded_dirs: vec!["duplicates".to_string()],
+            excluded_globs: Vec::new(),
+            file_types: Vec::new(),
+            duplicates_dir: PathBuf::from("duplicates"),
             duplicates_hash_threshold: 15,
+            duplicates_format_thresholds: BTreeMap::new(),
+            scan_max_file_size: None,
+            scan_min_dimension: None,
+            scan_soft_limit: 500_000,
         }
     }
 }
@@ -49,6 +79,8 @@ enum SelectionStrategy {
     Largest,
     /// Keep the smallest file (by size)
     Smallest,
+    /// Keep the file with the most camera-original name (ties: oldest)
+    Original,
 }
 
 #[derive(Parser, Debug)]
@@ -58,6 +90,10 @@ enum SelectionStrategy {
     about = "CLI for culling photos with advanced duplicate detection"
 )]
 struct Cli {
+    /// Config file to use instead of ~/.config/cullrs/config.toml
+    #[arg(long, global = true, value_name = "FILE")]
+    config: Option<PathBuf>,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -76,11 +112,47 @@ enum Commands {
         command: HistoryCmd,
     },
 
+    /// Reverse the moves recorded in a cull manifest
+    Undo {
+        /// Manifest written into the target directory by `cull` or `apply --move`
+        #[arg(long, value_name = "FILE")]
+        manifest: PathBuf,
+        /// Show what would be restored without moving anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+
     /// Configuration management
     Config {
         #[command(subcommand)]
         command: ConfigCmd,
     },
+
+    /// Print hashes for specific files and compare them pairwise
+    Hash {
+        /// Files to hash
+        #[arg(required = true, value_name = "FILE")]
+        files: Vec<PathBuf>,
+        /// Also compute perceptual hashes and pairwise Hamming distances
+        #[arg(long)]
+        perceptual: bool,
+    },
+
+    /// Compare two directory trees by image content
+    Diff {
+        /// First directory (e.g. the old backup)
+        #[arg(value_name = "DIR_A")]
+        dir_a: PathBuf,
+        /// Second directory (e.g. the current library)
+        #[arg(value_name = "DIR_B")]
+        dir_b: PathBuf,
+        /// Also match remaining images by perceptual hash
+        #[arg(long)]
+        perceptual: bool,
+        /// Hash similarity threshold for --perceptual (0-64, lower = more strict)
+        #[arg(long, requires = "perceptual", value_parser = clap::value_parser!(u32).range(0..=64))]
+        threshold: Option<u32>,
+    },
 }
 
 #[derive(Subcommand, Debug)]
@@ -91,8 +163,43 @@ enum DupeCMD {
         #[arg(short, long, value_name = "DIR")]
         path: PathBuf,
         /// Hash similarity threshold (0-64, lower = more strict)
-        #[arg(long)]
+        #[arg(long, value_parser = clap::value_parser!(u32).range(0..=64))]
         threshold: Option<u32>,
+        /// Only match byte-identical files (no decoding; --threshold is ignored)
+        #[arg(long, conflicts_with = "threshold")]
+        exact: bool,
+        /// With --exact, also compare matching files byte by byte
+        #[arg(long, requires = "exact")]
+        verify: bool,
+        /// Leave out paths matching this glob, relative to the directory (repeatable)
+        #[arg(long, value_name = "GLOB", value_parser = parse_glob)]
+        exclude: Vec<glob::Pattern>,
+        /// Only consider files matching this glob, relative to the directory (repeatable)
+        #[arg(long, value_name = "GLOB", value_parser = parse_glob)]
+        include: Vec<glob::Pattern>,
+        /// Skip files smaller than this, e.g. `200KB`
+        #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+        min_size: Option<u64>,
+        /// Skip files larger than this, e.g. `2MB`
+        #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+        max_size: Option<u64>,
+        /// Output format; `json` prints an editable plan for `duplicates apply`,
+        /// `csv` one row per file for spreadsheets
+        #[arg(long, alias = "output", value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+        /// Selection strategy for the keeper (listed first in each group)
+        #[arg(long, value_enum)]
+        strategy: Option<SelectionStrategy>,
+        /// Order groups by impact instead of discovery order
+        #[arg(long, value_enum)]
+        sort: Option<GroupOrder>,
+    },
+
+    /// Estimate scan size and duration without hashing everything
+    Estimate {
+        /// Directory to estimate
+        #[arg(short, long, value_name = "DIR")]
+        path: PathBuf,
     },
 
     /// Move duplicates into `<dir>/duplicates`
@@ -113,10 +220,126 @@ enum DupeCMD {
         #[arg(long)]
         force: bool,
         /// Hash similarity threshold (0-64, lower = more strict)
+        #[arg(long, value_parser = clap::value_parser!(u32).range(0..=64))]
+        threshold: Option<u32>,
+        /// Only match byte-identical files (no decoding; --threshold is ignored)
+        #[arg(long, conflicts_with = "threshold")]
+        exact: bool,
+        /// With --exact, also compare matching files byte by byte
+        #[arg(long, requires = "exact")]
+        verify: bool,
+        /// Leave out paths matching this glob, relative to the directory (repeatable)
+        #[arg(long, value_name = "GLOB", value_parser = parse_glob)]
+        exclude: Vec<glob::Pattern>,
+        /// Only consider files matching this glob, relative to the directory (repeatable)
+        #[arg(long, value_name = "GLOB", value_parser = parse_glob)]
+        include: Vec<glob::Pattern>,
+        /// Skip files smaller than this, e.g. `200KB`
+        #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+        min_size: Option<u64>,
+        /// Skip files larger than this, e.g. `2MB`
+        #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+        max_size: Option<u64>,
+        /// Only resolve groups at or above this confidence (0-100); leave the rest
+        #[arg(long, value_name = "PCT", value_parser = clap::value_parser!(u8).range(0..=100))]
+        min_confidence: Option<u8>,
+        /// Move duplicates away, or replace them in place with links to the keeper
+        #[arg(long, value_enum, default_value_t = CullMode::Move)]
+        mode: CullMode,
+    },
+
+    /// Execute a plan produced by `duplicates scan --format json`
+    #[command(group(ArgGroup::new("action").required(true).args(["move_files", "delete"])))]
+    Apply {
+        /// Plan file to execute
+        #[arg(value_name = "PLAN")]
+        plan: PathBuf,
+        /// Move culled files into the target directory
+        #[arg(long = "move")]
+        move_files: bool,
+        /// Permanently delete culled files
+        #[arg(long)]
+        delete: bool,
+        /// Directory to move duplicates into (default: `<root>/duplicates`)
+        #[arg(long, value_name = "DIR")]
+        target_dir: Option<PathBuf>,
+        /// Only show what would be done
+        #[arg(long)]
+        dry_run: bool,
+        /// Skip confirmation prompts
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Walk duplicate groups one at a time and choose what to keep
+    Review {
+        /// Directory to review
+        #[arg(short, long, value_name = "DIR")]
+        path: PathBuf,
+        /// Selection strategy for the suggested keeper
+        #[arg(long, value_enum)]
+        strategy: Option<SelectionStrategy>,
+        /// Hash similarity threshold (0-64, lower = more strict)
+        #[arg(long, value_parser = clap::value_parser!(u32).range(0..=64))]
+        threshold: Option<u32>,
+        /// Only match byte-identical files (no decoding; --threshold is ignored)
+        #[arg(long, conflicts_with = "threshold")]
+        exact: bool,
+        /// With --exact, also compare matching files byte by byte
+        #[arg(long, requires = "exact")]
+        verify: bool,
+        /// Leave out paths matching this glob, relative to the directory (repeatable)
+        #[arg(long, value_name = "GLOB", value_parser = parse_glob)]
+        exclude: Vec<glob::Pattern>,
+        /// Only consider files matching this glob, relative to the directory (repeatable)
+        #[arg(long, value_name = "GLOB", value_parser = parse_glob)]
+        include: Vec<glob::Pattern>,
+        /// Skip files smaller than this, e.g. `200KB`
+        #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+        min_size: Option<u64>,
+        /// Skip files larger than this, e.g. `2MB`
+        #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+        max_size: Option<u64>,
+        /// Permanently delete the files not kept instead of moving them
+        #[arg(long)]
+        delete: bool,
+        /// Directory to move duplicates into (default: `<dir>/duplicates`)
+        #[arg(long, value_name = "DIR", conflicts_with = "delete")]
+        target_dir: Option<PathBuf>,
+        /// Only show what would be done with the reviewed groups
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Report which images inside a .zip archive already exist in a directory
+    Archive {
+        /// ZIP archive to inspect (read in place, never extracted)
+        #[arg(value_name = "ARCHIVE")]
+        archive: PathBuf,
+        /// Library directory to compare against
+        #[arg(short, long, value_name = "DIR")]
+        path: PathBuf,
+        /// Also match remaining images by perceptual hash
         #[arg(long)]
+        perceptual: bool,
+        /// Hash similarity threshold for --perceptual (0-64, lower = more strict)
+        #[arg(long, requires = "perceptual", value_parser = clap::value_parser!(u32).range(0..=64))]
         threshold: Option<u32>,
     },
 
+    /// List zero-byte and tiny image files, optionally deleting them
+    Degenerate {
+        /// Directory to check
+        #[arg(short, long, value_name = "DIR")]
+        path: PathBuf,
+        /// Permanently delete the files found
+        #[arg(long)]
+        delete: bool,
+        /// Skip confirmation prompts
+        #[arg(long)]
+        force: bool,
+    },
+
     /// Permanently delete duplicate images
     Delete {
         /// Directory to cull
@@ -129,11 +352,209 @@ enum DupeCMD {
         #[arg(long)]
         force: bool,
         /// Hash similarity threshold (0-64, lower = more strict)
-        #[arg(long)]
+        #[arg(long, value_parser = clap::value_parser!(u32).range(0..=64))]
         threshold: Option<u32>,
+        /// Only match byte-identical files (no decoding; --threshold is ignored)
+        #[arg(long, conflicts_with = "threshold")]
+        exact: bool,
+        /// With --exact, also compare matching files byte by byte
+        #[arg(long, requires = "exact")]
+        verify: bool,
+        /// Leave out paths matching this glob, relative to the directory (repeatable)
+        #[arg(long, value_name = "GLOB", value_parser = parse_glob)]
+        exclude: Vec<glob::Pattern>,
+        /// Only consider files matching this glob, relative to the directory (repeatable)
+        #[arg(long, value_name = "GLOB", value_parser = parse_glob)]
+        include: Vec<glob::Pattern>,
+        /// Skip files smaller than this, e.g. `200KB`
+        #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+        min_size: Option<u64>,
+        /// Skip files larger than this, e.g. `2MB`
+        #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+        max_size: Option<u64>,
+        /// Only resolve groups at or above this confidence (0-100); leave the rest
+        #[arg(long, value_name = "PCT", value_parser = clap::value_parser!(u8).range(0..=100))]
+        min_confidence: Option<u8>,
     },
 }
 
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum GroupOrder {
+    /// Most reclaimable bytes first
+    Bytes,
+    /// Most members first
+    Count,
+    /// Most similar (smallest distance) first
+    Similarity,
+    /// Alphabetically by keeper path
+    Path,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum OutputFormat {
+    /// Human-readable listing
+    Text,
+    /// Machine-readable cull plan
+    Json,
+    /// One row per file with its group, role, size and content hash
+    Csv,
+}
+
+/// A cull plan as printed by `duplicates scan --format json`. Humans or
+/// scripts may edit `keep`/`cull` before handing it to `duplicates apply`.
+#[derive(Serialize, Deserialize, Debug)]
+struct CullPlan {
+    root: PathBuf,
+    threshold: u32,
+    strategy: SelectionStrategy,
+    groups: Vec<PlanGroup>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct PlanGroup {
+    id: usize,
+    /// Informational only; ignored by `duplicates apply`
+    #[serde(default)]
+    confidence: f32,
+    /// Informational only; ignored by `duplicates apply`
+    #[serde(default)]
+    max_distance: u32,
+    keep: PathBuf,
+    cull: Vec<PathBuf>,
+    /// RAW halves of culled RAW+JPEG pairs. `duplicates apply` culls each
+    /// RAW together with its JPEG, and touches no RAW file not listed here.
+    #[serde(default)]
+    raw_pairs: Vec<RawPair>,
+    /// Per-file metadata, keeper first, then RAW pairs. Informational only;
+    /// ignored by `duplicates apply`, so edit `keep`/`cull` rather than this list.
+    #[serde(default)]
+    files: Vec<PlanFile>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct RawPair {
+    jpeg: PathBuf,
+    raw: PathBuf,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct PlanFile {
+    path: PathBuf,
+    size: u64,
+    /// BLAKE3 of the file contents, hex encoded
+    content_hash: String,
+}
+
+// One row of `duplicates scan --format csv`
+#[derive(Serialize)]
+struct ScanCsvRow<'a> {
+    group_id: usize,
+    confidence: f32,
+    max_distance: u32,
+    role: &'static str,
+    path: &'a Path,
+    size: u64,
+    content_hash: &'a str,
+}
+
+/// Written into the target directory by every cull that moves files, so the
+/// whole batch can be reversed with `cullrs undo --manifest <file>`.
+#[derive(Serialize, Deserialize, Debug)]
+struct MoveManifest {
+    created: String,
+    root: PathBuf,
+    moves: Vec<ManifestMove>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct ManifestMove {
+    from: PathBuf,
+    to: PathBuf,
+}
+
+impl MoveManifest {
+    fn new(root: &Path) -> Self {
+        Self {
+            created: Utc::now().to_rfc3339(),
+            root: std::path::absolute(root).unwrap_or_else(|_| root.to_path_buf()),
+            moves: Vec::new(),
+        }
+    }
+
+    // Paths are stored absolute so undo works from any working directory
+    fn record(&mut self, from: &Path, to: &Path) {
+        let absolute = |p: &Path| std::path::absolute(p).unwrap_or_else(|_| p.to_path_buf());
+        self.moves.push(ManifestMove {
+            from: absolute(from),
+            to: absolute(to),
+        });
+    }
+
+    fn load(path: &Path) -> Result<Self> {
+        let data = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read manifest {:?}", path))?;
+        serde_json::from_str(&data).with_context(|| format!("Invalid manifest {:?}", path))
+    }
+
+    fn save(&self, target_dir: &Path) -> Result<PathBuf> {
+        let path = target_dir.join(format!(
+            "cull-manifest-{}.json",
+            Local::now().format("%Y%m%d-%H%M%S-%3f")
+        ));
+        self.write(&path)?;
+        Ok(path)
+    }
+
+    fn write(&self, path: &Path) -> Result<()> {
+        fs::write(path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write manifest {:?}", path))
+    }
+}
+
+/// What happens to the non-kept files of a duplicate group.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CullAction {
+    Move,
+    Delete,
+    Hardlink,
+    Symlink,
+}
+
+impl CullAction {
+    fn history_label(&self) -> &'static str {
+        match self {
+            CullAction::Move => "moved",
+            CullAction::Delete => "deleted",
+            CullAction::Hardlink => "hardlinked",
+            CullAction::Symlink => "symlinked",
+        }
+    }
+
+    fn is_link(&self) -> bool {
+        matches!(self, CullAction::Hardlink | CullAction::Symlink)
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum CullMode {
+    /// Move duplicates into the target directory
+    Move,
+    /// Replace duplicates with hardlinks to the keeper (same filesystem only)
+    Hardlink,
+    /// Replace duplicates with symlinks to the keeper
+    Symlink,
+}
+
+impl From<CullMode> for CullAction {
+    fn from(mode: CullMode) -> Self {
+        match mode {
+            CullMode::Move => CullAction::Move,
+            CullMode::Hardlink => CullAction::Hardlink,
+            CullMode::Symlink => CullAction::Symlink,
+        }
+    }
+}
+
 #[derive(Subcommand, Debug)]
 enum HistoryCmd {
     /// List all cull history records
@@ -166,12 +587,42 @@ enum ConfigCmd {
         /// Hash similarity threshold
         #[arg(long)]
         threshold: Option<u32>,
+        /// Threshold override for one image format, e.g. `png=4` (repeatable)
+        #[arg(long, value_name = "FORMAT=N", value_parser = parse_format_threshold)]
+        format_threshold: Vec<(String, u32)>,
+        /// Remove all per-format threshold overrides
+        #[arg(long)]
+        clear_format_thresholds: bool,
         /// Default selection strategy
         #[arg(long, value_enum)]
         strategy: Option<SelectionStrategy>,
         /// Auto-confirm destructive operations
         #[arg(long)]
         auto_confirm: Option<bool>,
+        /// Directory culled files are moved into (relative to the scanned directory, or absolute)
+        #[arg(long, value_name = "PATH")]
+        duplicates_dir: Option<PathBuf>,
+        /// Skip files larger than this, e.g. `50MB` (0 = no limit)
+        #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+        max_file_size: Option<u64>,
+        /// Skip images smaller than this many pixels on either side (0 = no limit)
+        #[arg(long, value_name = "PX")]
+        min_dimension: Option<u32>,
+        /// Ask before scanning more than this many images (0 = no limit)
+        #[arg(long, value_name = "N")]
+        soft_limit: Option<u64>,
+        /// Glob of paths to leave out of scans, relative to the scanned directory (repeatable)
+        #[arg(long, value_name = "GLOB")]
+        exclude_glob: Vec<String>,
+        /// Remove all excluded globs
+        #[arg(long)]
+        clear_exclude_globs: bool,
+        /// Only scan this image or video type, e.g. `jpg` or `mp4` (repeatable)
+        #[arg(long, value_name = "EXT")]
+        file_type: Vec<String>,
+        /// Scan all supported image types again
+        #[arg(long)]
+        clear_file_types: bool,
     },
     /// Reset configuration to defaults
     Reset,
@@ -179,11 +630,39 @@ enum ConfigCmd {
 
 fn main() -> Result<()> {
     let cli = Cli::parse();
+    if let Some(path) = cli.config {
+        // `config set` and `config reset` may create it; anything else
+        // reading a missing file is most likely a typo
+        let creates = matches!(
+            cli.command,
+            Commands::Config {
+                command: ConfigCmd::Set { .. } | ConfigCmd::Reset
+            }
+        );
+        if !creates && !path.is_file() {
+            anyhow::bail!("Config file does not exist: {}", path.display());
+        }
+        CONFIG_PATH_OVERRIDE.set(path).unwrap();
+    }
+    // Surface a broken config once up front; `config` itself must still run so
+    // it can be reset.
+    if !matches!(cli.command, Commands::Config { .. }) {
+        let path = get_config_path()?;
+        load_config(&path).with_context(|| format!("Invalid config file {:?}", path))?;
+    }
 
     match cli.command {
         Commands::Config { command } => handle_config_command(command),
         Commands::Duplicates { command } => handle_duplicates_command(command),
         Commands::History { command } => handle_history_command(command),
+        Commands::Undo { manifest, dry_run } => handle_undo_command(&manifest, dry_run),
+        Commands::Hash { files, perceptual } => handle_hash_command(&files, perceptual),
+        Commands::Diff {
+            dir_a,
+            dir_b,
+            perceptual,
+            threshold,
+        } => handle_diff_command(&dir_a, &dir_b, perceptual, threshold),
     }
 }
 
@@ -193,7 +672,7 @@ fn handle_config_command(command: ConfigCmd) -> Result<()> {
     match command {
         ConfigCmd::Show => {
             let config = load_config(&config_path).unwrap_or_default();
-            println!("Current configuration:");
+            println!("Current configuration ({}):", config_path.display());
             println!("  [General] Auto confirm: {}", config.auto_confirm);
             println!(
                 "  [General] Selection strategy: {:?}",
@@ -203,15 +682,61 @@ fn handle_config_command(command: ConfigCmd) -> Result<()> {
                 "  [General] Excluded directories: {:?}",
                 config.excluded_dirs
             );
+            println!("  [Scan] Excluded globs: {:?}", config.excluded_globs);
+            println!(
+                "  [Scan] File types: {}",
+                if config.file_types.is_empty() {
+                    "all supported".to_string()
+                } else {
+                    config.file_types.join(", ")
+                }
+            );
+            println!(
+                "  [Scan] Max file size: {}",
+                config
+                    .scan_max_file_size
+                    .map_or("no limit".to_string(), format_bytes)
+            );
+            println!(
+                "  [Scan] Min dimension: {}",
+                config
+                    .scan_min_dimension
+                    .map_or("no limit".to_string(), |px| format!("{}px", px))
+            );
+            println!(
+                "  [Scan] Soft limit: {}",
+                match config.scan_soft_limit {
+                    0 => "no limit".to_string(),
+                    n => format!("{} images", n),
+                }
+            );
+            println!(
+                "  [Duplicates] Target directory: {}",
+                config.duplicates_dir.display()
+            );
             println!(
                 "  [Duplicates] Hash threshold: {}",
                 config.duplicates_hash_threshold
             );
+            println!(
+                "  [Duplicates] Format thresholds: {}",
+                describe_format_thresholds(&config.duplicates_format_thresholds)
+            );
         }
         ConfigCmd::Set {
             threshold,
+            format_threshold,
+            clear_format_thresholds,
             strategy,
             auto_confirm,
+            duplicates_dir,
+            max_file_size,
+            min_dimension,
+            soft_limit,
+            exclude_glob,
+            clear_exclude_globs,
+            file_type,
+            clear_file_types,
         } => {
             let mut config = load_config(&config_path).unwrap_or_default();
 
@@ -221,12 +746,50 @@ fn handle_config_command(command: ConfigCmd) -> Result<()> {
                 }
                 config.duplicates_hash_threshold = t;
             }
+            if clear_format_thresholds {
+                config.duplicates_format_thresholds.clear();
+            }
+            for (format, t) in format_threshold {
+                config.duplicates_format_thresholds.insert(format, t);
+            }
             if let Some(s) = strategy {
                 config.selection_strategy = s;
             }
             if let Some(ac) = auto_confirm {
                 config.auto_confirm = ac;
             }
+            if let Some(dir) = duplicates_dir {
+                validate_duplicates_dir(&dir)?;
+                config.duplicates_dir = dir;
+            }
+            if let Some(bytes) = max_file_size {
+                config.scan_max_file_size = (bytes > 0).then_some(bytes);
+            }
+            if let Some(px) = min_dimension {
+                config.scan_min_dimension = (px > 0).then_some(px);
+            }
+            if let Some(n) = soft_limit {
+                config.scan_soft_limit = n;
+            }
+            if clear_exclude_globs {
+                config.excluded_globs.clear();
+            }
+            compile_globs(&exclude_glob)?;
+            config.excluded_globs.extend(exclude_glob);
+            if clear_file_types {
+                config.file_types.clear();
+            }
+            for ext in file_type {
+                let ext = ext.trim_start_matches('.').to_lowercase();
+                if ImageFormat::from_extension(&ext).is_none()
+                    && !VIDEO_EXTENSIONS.contains(&ext.as_str())
+                {
+                    anyhow::bail!("Unknown image or video type '{}'", ext);
+                }
+                if !config.file_types.contains(&ext) {
+                    config.file_types.push(ext);
+                }
+            }
 
             save_config(&config_path, &config)?;
             println!("Configuration updated!");
@@ -244,25 +807,165 @@ fn handle_duplicates_command(command: DupeCMD) -> Result<()> {
     let config = load_config(&get_config_path()?).unwrap_or_default();
 
     match command {
-        DupeCMD::Scan { path, threshold } => {
+        DupeCMD::Scan {
+            path,
+            threshold,
+            exact,
+            verify,
+            exclude,
+            include,
+            min_size,
+            max_size,
+            format,
+            strategy,
+            sort,
+        } => {
+            let filters = ScanFilters {
+                exclude,
+                include,
+                min_size,
+                max_size,
+            };
             validate_directory(&path)?;
-            println!("▶ Scanning for duplicates in: {}", path.display());
-
             let threshold = threshold.unwrap_or(config.duplicates_hash_threshold);
-            let groups = find_duplicates(&path, threshold)?;
+            let json = format != OutputFormat::Text;
+
+            // Absolute paths keep a JSON plan valid from any working directory,
+            // and status goes to stderr so the output can be redirected cleanly.
+            let root = if json {
+                fs::canonicalize(&path).with_context(|| format!("Failed to resolve {:?}", path))?
+            } else {
+                path.clone()
+            };
+            if json {
+                eprintln!("▶ Scanning for duplicates in: {}", root.display());
+            } else {
+                println!("▶ Scanning for duplicates in: {}", root.display());
+            }
+
+            let mut groups =
+                find_groups(&root, exact, verify, threshold, &config, &filters, false)?;
+            let selection_strategy = strategy.unwrap_or(config.selection_strategy);
+            for group in &mut groups {
+                sort_group_by_strategy(&mut group.files, &selection_strategy);
+            }
+            if let Some(order) = sort {
+                sort_groups(&mut groups, order);
+            }
+
+            if json {
+                let plan = CullPlan {
+                    root,
+                    threshold,
+                    strategy: selection_strategy,
+                    groups: groups
+                        .into_iter()
+                        .enumerate()
+                        .map(|(i, mut group)| {
+                            let mut pairs = collect_raw_pairs(std::slice::from_ref(&group.files));
+                            let raw_pairs: Vec<RawPair> =
+                                group.files[1..]
+                                    .iter()
+                                    .flat_map(|jpeg| {
+                                        pairs.remove(jpeg).unwrap_or_default().into_iter().map(
+                                            |raw| RawPair {
+                                                jpeg: jpeg.clone(),
+                                                raw,
+                                            },
+                                        )
+                                    })
+                                    .collect();
+                            let listed: Vec<PathBuf> = group
+                                .files
+                                .iter()
+                                .cloned()
+                                .chain(raw_pairs.iter().map(|pair| pair.raw.clone()))
+                                .collect();
+                            let files = describe_plan_files(&listed)?;
+                            Ok(PlanGroup {
+                                id: i + 1,
+                                confidence: group.confidence,
+                                max_distance: group.max_distance,
+                                keep: group.files.remove(0),
+                                cull: group.files,
+                                raw_pairs,
+                                files,
+                            })
+                        })
+                        .collect::<Result<_>>()?,
+                };
+                if format == OutputFormat::Csv {
+                    write_plan_csv(&plan, io::stdout().lock())?;
+                } else {
+                    println!("{}", serde_json::to_string_pretty(&plan)?);
+                }
+                return Ok(());
+            }
+
             if groups.is_empty() {
                 println!("No duplicates found.");
             } else {
                 println!("Found {} duplicate group(s):", groups.len());
                 for (i, group) in groups.iter().enumerate() {
-                    println!(" Group {}:", i + 1);
-                    for file in group {
+                    println!(
+                        " Group {} ({} files, {} reclaimable, max distance {}, {:.0}% confidence):",
+                        i + 1,
+                        group.files.len(),
+                        format_bytes(group.reclaimable_bytes()),
+                        group.max_distance,
+                        group.confidence * 100.0
+                    );
+                    for file in &group.files {
                         println!("   ▶ {}", file.display());
+                        for raw in raw_companions(file) {
+                            println!("     ↳ RAW pair {}", raw.display());
+                        }
                     }
                 }
             }
         }
 
+        DupeCMD::Estimate { path } => {
+            validate_directory(&path)?;
+            println!("▶ Estimating scan of: {}", path.display());
+
+            let estimate = estimate_scan(&path)?;
+            println!(
+                "  {} files, {} total, {} images",
+                estimate.file_count,
+                format_bytes(estimate.total_bytes),
+                estimate.image_count
+            );
+
+            println!("\n  By extension:");
+            let mut by_extension: Vec<_> = estimate.by_extension.iter().collect();
+            by_extension.sort_by_key(|(_, (_, bytes))| std::cmp::Reverse(*bytes));
+            for (ext, (count, bytes)) in by_extension {
+                println!(
+                    "   {:<8} {:>8} files {:>12}",
+                    ext,
+                    count,
+                    format_bytes(*bytes)
+                );
+            }
+
+            println!("\n  Largest folders:");
+            let mut folders: Vec<_> = estimate.by_folder.iter().collect();
+            folders.sort_by_key(|(_, bytes)| std::cmp::Reverse(**bytes));
+            for (folder, bytes) in folders.into_iter().take(5) {
+                println!("   {:>12}  {}", format_bytes(*bytes), folder.display());
+            }
+
+            match estimate.hashing_time {
+                Some(eta) => println!(
+                    "\n⏱ Estimated hashing time: ~{} (sampled {} images)",
+                    format_duration(eta),
+                    estimate.sampled
+                ),
+                None => println!("\n⏱ No decodable images sampled; cannot estimate time."),
+            }
+        }
+
         DupeCMD::Cull {
             path,
             dry_run,
@@ -270,22 +973,44 @@ fn handle_duplicates_command(command: DupeCMD) -> Result<()> {
             strategy,
             force,
             threshold,
+            exact,
+            verify,
+            exclude,
+            include,
+            min_size,
+            max_size,
+            min_confidence,
+            mode,
         } => {
+            let filters = ScanFilters {
+                exclude,
+                include,
+                min_size,
+                max_size,
+            };
             validate_directory(&path)?;
+            let action = CullAction::from(mode);
 
-            let target_dir = target_dir.unwrap_or_else(|| path.join("duplicates"));
-            validate_target_directory(&path, &target_dir)?;
-
-            if !force && !config.auto_confirm && !dry_run {
-                if !confirm_action(&format!("Move duplicates to '{}'?", target_dir.display()))? {
-                    println!("Operation cancelled.");
-                    return Ok(());
-                }
+            let target_dir = target_dir.unwrap_or_else(|| path.join(&config.duplicates_dir));
+            if action == CullAction::Move {
+                validate_target_directory(&path, &target_dir, &config)?;
             }
 
             println!("▶ Culling duplicates in: {}", path.display());
             let threshold = threshold.unwrap_or(config.duplicates_hash_threshold);
-            let mut groups = find_duplicates(&path, threshold)?;
+            let (confident, ambiguous) = partition_by_confidence(
+                find_groups(&path, exact, verify, threshold, &config, &filters, force)?,
+                min_confidence,
+            );
+            if !ambiguous.is_empty() {
+                println!(
+                    "⏭  Leaving {} group(s) below {}% confidence for manual review",
+                    ambiguous.len(),
+                    min_confidence.unwrap_or_default()
+                );
+            }
+            let mut groups: Vec<Vec<PathBuf>> =
+                confident.into_iter().map(|group| group.files).collect();
             if groups.is_empty() {
                 println!("No duplicates found.");
                 return Ok(());
@@ -296,136 +1021,767 @@ fn handle_duplicates_command(command: DupeCMD) -> Result<()> {
                 sort_group_by_strategy(group, &selection_strategy);
             }
 
-            if !dry_run {
-                fs::create_dir_all(&target_dir)
-                    .with_context(|| format!("Failed to create directory {:?}", target_dir))?;
-            }
+            let raw_pairs = collect_raw_pairs(&groups);
 
-            let history_file = path.join(".history.jsonl");
-            let mut history_out = if dry_run {
-                None
-            } else {
-                Some(
-                    OpenOptions::new()
-                        .create(true)
-                        .append(true)
-                        .open(&history_file)
-                        .with_context(|| {
-                            format!("Failed to open history file {:?}", history_file)
-                        })?,
-                )
+            let prompt = match action {
+                CullAction::Move => format!(
+                    "Move {} file(s) to '{}'?",
+                    cull_count(&groups, &raw_pairs),
+                    target_dir.display()
+                ),
+                _ => format!(
+                    "Replace {} duplicate(s) with {} to their keepers?",
+                    cull_count(&groups, &RawPairs::new()),
+                    if action == CullAction::Hardlink {
+                        "hardlinks"
+                    } else {
+                        "symlinks"
+                    }
+                ),
             };
+            if !force && !config.auto_confirm && !dry_run && !confirm_action(&prompt)? {
+                println!("Operation cancelled.");
+                return Ok(());
+            }
 
-            for (i, group) in groups.iter().enumerate() {
-                println!("\n✨ Group {}:", i + 1);
-                println!("   🏆 Keeping → {}", group[0].display());
-                let retained = group[0].to_string_lossy().into_owned();
-                let mut culled_paths = Vec::new();
+            let show_timestamp = matches!(
+                selection_strategy,
+                SelectionStrategy::Oldest | SelectionStrategy::Newest
+            );
+            execute_groups(
+                &path,
+                &groups,
+                &raw_pairs,
+                action,
+                &target_dir,
+                dry_run,
+                show_timestamp,
+            )?;
+        }
 
-                for dup in &group[1..] {
-                    culled_paths.push(dup.to_string_lossy().into_owned());
-                    if dry_run {
-                        println!(
-                            "   📦 [dry-run] MOVE {} → {}",
-                            dup.display(),
-                            target_dir.display()
-                        );
-                    } else {
-                        let dest = get_unique_destination(&target_dir, dup)?;
-                        fs::rename(dup, &dest)
-                            .with_context(|| format!("Failed to move {:?} → {:?}", dup, dest))?;
-                        println!("   📦 Moved {} → {}", dup.display(), dest.display());
-                    }
-                }
+        DupeCMD::Apply {
+            plan,
+            move_files,
+            delete: _,
+            target_dir,
+            dry_run,
+            force,
+        } => {
+            let content = fs::read_to_string(&plan)
+                .with_context(|| format!("Failed to read plan {:?}", plan))?;
+            let plan: CullPlan = serde_json::from_str(&content)
+                .with_context(|| format!("Invalid plan file {:?}", plan))?;
+            validate_directory(&plan.root)?;
 
-                if let Some(out) = history_out.as_mut() {
-                    let record = CullHistoryRecord {
-                        timestamp: Utc::now().to_rfc3339(),
-                        retained,
-                        culled: culled_paths,
-                        action: "moved".to_string(),
-                    };
-                    writeln!(out, "{}", serde_json::to_string(&record)?)?;
-                }
+            let action = if move_files {
+                CullAction::Move
+            } else {
+                CullAction::Delete
+            };
+            let target_dir = target_dir.unwrap_or_else(|| plan.root.join(&config.duplicates_dir));
+            if action == CullAction::Move {
+                validate_target_directory(&plan.root, &target_dir, &config)?;
+            }
+
+            let (groups, raw_pairs) = validate_plan(&plan)?;
+            if groups.is_empty() {
+                println!("Plan has nothing to apply.");
+                return Ok(());
             }
 
-            if dry_run {
-                println!("\n⚠️  Dry-run only; no files were changed.");
+            let count = cull_count(&groups, &raw_pairs);
+            let prompt = if action == CullAction::Move {
+                format!("Move {} file(s) to '{}'?", count, target_dir.display())
             } else {
-                println!(
-                    "\n✅ Recorded cull history in {}",
-                    path.join(".history.jsonl").display()
-                );
+                format!(
+                    "Permanently delete {} file(s)? This cannot be undone!",
+                    count
+                )
+            };
+            if !force && !config.auto_confirm && !dry_run && !confirm_action(&prompt)? {
+                println!("Operation cancelled.");
+                return Ok(());
             }
+
+            println!("▶ Applying plan to: {}", plan.root.display());
+            execute_groups(
+                &plan.root,
+                &groups,
+                &raw_pairs,
+                action,
+                &target_dir,
+                dry_run,
+                false,
+            )?;
         }
 
-        DupeCMD::Delete {
+        DupeCMD::Review {
             path,
             strategy,
-            force,
             threshold,
+            exact,
+            verify,
+            exclude,
+            include,
+            min_size,
+            max_size,
+            delete,
+            target_dir,
+            dry_run,
         } => {
+            let filters = ScanFilters {
+                exclude,
+                include,
+                min_size,
+                max_size,
+            };
             validate_directory(&path)?;
-
-            if !force && !config.auto_confirm {
-                if !confirm_action("Permanently delete duplicate files? This cannot be undone!")? {
-                    println!("Operation cancelled.");
-                    return Ok(());
-                }
+            let action = if delete {
+                CullAction::Delete
+            } else {
+                CullAction::Move
+            };
+            let target_dir = target_dir.unwrap_or_else(|| path.join(&config.duplicates_dir));
+            if action == CullAction::Move {
+                validate_target_directory(&path, &target_dir, &config)?;
             }
 
-            println!("▶ Deleting duplicates in: {}", path.display());
+            println!("▶ Reviewing duplicates in: {}", path.display());
             let threshold = threshold.unwrap_or(config.duplicates_hash_threshold);
-            let mut groups = find_duplicates(&path, threshold)?;
+            let mut groups =
+                find_groups(&path, exact, verify, threshold, &config, &filters, false)?;
             if groups.is_empty() {
                 println!("No duplicates found.");
                 return Ok(());
             }
-
             let selection_strategy = strategy.unwrap_or(config.selection_strategy);
             for group in &mut groups {
-                sort_group_by_strategy(group, &selection_strategy);
+                sort_group_by_strategy(&mut group.files, &selection_strategy);
             }
 
-            let history_file = path.join(".history.jsonl");
-            let mut history_out = OpenOptions::new()
-                .create(true)
-                .append(true)
-                .open(&history_file)
-                .with_context(|| format!("Failed to open history file {:?}", history_file))?;
-
-            for (i, group) in groups.iter().enumerate() {
-                println!("\n✨ Group {}:", i + 1);
-                println!("   🏆 Keeping → {}", group[0].display());
-                let retained = group[0].to_string_lossy().into_owned();
-                let mut culled_paths = Vec::new();
-
-                for dup in &group[1..] {
-                    culled_paths.push(dup.to_string_lossy().into_owned());
-                    fs::remove_file(dup)
-                        .with_context(|| format!("Failed to delete {}", dup.display()))?;
-                    println!("   🗑️  Deleted {}", dup.display());
-                }
-
-                let record = CullHistoryRecord {
-                    timestamp: Utc::now().to_rfc3339(),
-                    retained,
-                    culled: culled_paths,
-                    action: "deleted".to_string(),
-                };
-                writeln!(history_out, "{}", serde_json::to_string(&record)?)?;
+            let decisions = review_groups(&groups)?;
+            let raw_pairs = collect_raw_pairs(&decisions);
+            let count = cull_count(&decisions, &raw_pairs);
+            if count == 0 {
+                println!("\nNothing selected; no files were changed.");
+                return Ok(());
             }
 
-            println!(
-                "\n✅ Recorded cull history in {}",
-                path.join(".history.jsonl").display()
-            );
+            let prompt = if action == CullAction::Move {
+                format!("Move {} file(s) to '{}'?", count, target_dir.display())
+            } else {
+                format!(
+                    "Permanently delete {} file(s)? This cannot be undone!",
+                    count
+                )
+            };
+            if !dry_run && !confirm_action(&format!("\n{}", prompt))? {
+                println!("Operation cancelled.");
+                return Ok(());
+            }
+            execute_groups(
+                &path,
+                &decisions,
+                &raw_pairs,
+                action,
+                &target_dir,
+                dry_run,
+                false,
+            )?;
         }
-    }
+
+        DupeCMD::Archive {
+            archive,
+            path,
+            perceptual,
+            threshold,
+        } => handle_archive_command(&archive, &path, perceptual, threshold)?,
+
+        DupeCMD::Degenerate {
+            path,
+            delete,
+            force,
+        } => {
+            validate_directory(&path)?;
+            println!("▶ Looking for degenerate files in: {}", path.display());
+
+            let degenerate = scan_directory(&path, &ScanFilters::default(), force)?.degenerate;
+            if degenerate.is_empty() {
+                println!("No degenerate files found.");
+                return Ok(());
+            }
+            println!(
+                "Found {} file(s) under {}:",
+                degenerate.len(),
+                format_bytes(DEGENERATE_FILE_SIZE)
+            );
+            for file in &degenerate {
+                let size = fs::metadata(file).map(|m| m.len()).unwrap_or(0);
+                println!("   ▶ {} ({})", file.display(), format_bytes(size));
+            }
+            if !delete {
+                return Ok(());
+            }
+
+            if !force
+                && !config.auto_confirm
+                && !confirm_action(&format!(
+                    "Permanently delete {} degenerate file(s)?",
+                    degenerate.len()
+                ))?
+            {
+                println!("Operation cancelled.");
+                return Ok(());
+            }
+
+            let mut culled_paths = Vec::new();
+            for file in &degenerate {
+                fs::remove_file(file)
+                    .with_context(|| format!("Failed to delete {}", file.display()))?;
+                println!("   🗑️  Deleted {}", file.display());
+                culled_paths.push(file.to_string_lossy().into_owned());
+            }
+
+            let history_file = path.join(".history.jsonl");
+            let mut history_out = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&history_file)
+                .with_context(|| format!("Failed to open history file {:?}", history_file))?;
+            let record = CullHistoryRecord {
+                timestamp: Utc::now().to_rfc3339(),
+                retained: String::new(),
+                culled: culled_paths,
+                action: CullAction::Delete.history_label().to_string(),
+            };
+            writeln!(history_out, "{}", serde_json::to_string(&record)?)?;
+            println!("\n✅ Recorded cull history in {}", history_file.display());
+        }
+
+        DupeCMD::Delete {
+            path,
+            strategy,
+            force,
+            threshold,
+            exact,
+            verify,
+            exclude,
+            include,
+            min_size,
+            max_size,
+            min_confidence,
+        } => {
+            let filters = ScanFilters {
+                exclude,
+                include,
+                min_size,
+                max_size,
+            };
+            validate_directory(&path)?;
+
+            println!("▶ Deleting duplicates in: {}", path.display());
+            let threshold = threshold.unwrap_or(config.duplicates_hash_threshold);
+            let (confident, ambiguous) = partition_by_confidence(
+                find_groups(&path, exact, verify, threshold, &config, &filters, force)?,
+                min_confidence,
+            );
+            if !ambiguous.is_empty() {
+                println!(
+                    "⏭  Leaving {} group(s) below {}% confidence for manual review",
+                    ambiguous.len(),
+                    min_confidence.unwrap_or_default()
+                );
+            }
+            let mut groups: Vec<Vec<PathBuf>> =
+                confident.into_iter().map(|group| group.files).collect();
+            if groups.is_empty() {
+                println!("No duplicates found.");
+                return Ok(());
+            }
+
+            let selection_strategy = strategy.unwrap_or(config.selection_strategy);
+            for group in &mut groups {
+                sort_group_by_strategy(group, &selection_strategy);
+            }
+
+            let show_timestamp = matches!(
+                selection_strategy,
+                SelectionStrategy::Oldest | SelectionStrategy::Newest
+            );
+            let raw_pairs = collect_raw_pairs(&groups);
+            if !force
+                && !config.auto_confirm
+                && !confirm_action(&format!(
+                    "Permanently delete {} file(s)? This cannot be undone!",
+                    cull_count(&groups, &raw_pairs)
+                ))?
+            {
+                println!("Operation cancelled.");
+                return Ok(());
+            }
+
+            execute_groups(
+                &path,
+                &groups,
+                &raw_pairs,
+                CullAction::Delete,
+                &path.join(&config.duplicates_dir),
+                false,
+                show_timestamp,
+            )?;
+        }
+    }
+    Ok(())
+}
+
+// Moves, deletes or links every file after the first (the keeper) in each
+// group and appends one history record per group to `<root>/.history.jsonl`.
+fn execute_groups(
+    root: &Path,
+    groups: &[Vec<PathBuf>],
+    raw_pairs: &RawPairs,
+    action: CullAction,
+    target_dir: &Path,
+    dry_run: bool,
+    show_timestamp: bool,
+) -> Result<()> {
+    if !dry_run && action == CullAction::Move {
+        fs::create_dir_all(target_dir)
+            .with_context(|| format!("Failed to create directory {:?}", target_dir))?;
+    }
+    // Linking to a symlink keeper can chain links into a loop that no longer
+    // points at any image data, so refuse before anything changes
+    if action.is_link()
+        && !dry_run
+        && let Some(group) = groups.iter().find(|group| is_symlink(&group[0]))
+    {
+        anyhow::bail!(
+            "Refusing to link duplicates to {}: the keeper is itself a symlink",
+            group[0].display()
+        );
+    }
+    // Check every pair up front so a hardlink cull never stops half-way
+    if action == CullAction::Hardlink {
+        for group in groups {
+            for dup in &group[1..] {
+                if !same_filesystem(&group[0], dup)? {
+                    anyhow::bail!(
+                        "Cannot hardlink {} to {}: they are on different filesystems (use --mode symlink or move)",
+                        dup.display(),
+                        group[0].display()
+                    );
+                }
+            }
+        }
+    }
+    let mut reclaimed = 0;
+
+    let history_file = root.join(".history.jsonl");
+    let mut history_out = if dry_run {
+        None
+    } else {
+        Some(
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&history_file)
+                .with_context(|| format!("Failed to open history file {:?}", history_file))?,
+        )
+    };
+
+    let mut manifest = (action == CullAction::Move && !dry_run).then(|| MoveManifest::new(root));
+    // Run the groups in a closure so the manifest is still written for the
+    // moves that happened if a later one fails.
+    let result = (|| -> Result<()> {
+        for (i, group) in groups.iter().enumerate() {
+            println!("\n✨ Group {}:", i + 1);
+            println!("   🏆 Keeping → {}", group[0].display());
+            if show_timestamp {
+                println!("   🕒 {}", describe_timestamp(&group[0]));
+            }
+            let retained = group[0].to_string_lossy().into_owned();
+            let mut culled_paths = Vec::new();
+
+            for dup in &group[1..] {
+                culled_paths.push(dup.to_string_lossy().into_owned());
+                // RAW halves of a RAW+JPEG pair are moved or deleted with it
+                let companions = raw_pairs.get(dup).map_or(&[][..], Vec::as_slice);
+                if !action.is_link() {
+                    culled_paths
+                        .extend(companions.iter().map(|p| p.to_string_lossy().into_owned()));
+                }
+                match (action, dry_run) {
+                    (CullAction::Move, true) => {
+                        for file in std::iter::once(dup).chain(companions) {
+                            println!(
+                                "   📦 [dry-run] MOVE {} → {}",
+                                file.display(),
+                                target_dir.display()
+                            );
+                        }
+                    }
+                    (CullAction::Move, false) => {
+                        for (from, to) in move_with_companions(dup, companions, target_dir)? {
+                            println!("   📦 Moved {} → {}", from.display(), to.display());
+                            if let Some(manifest) = manifest.as_mut() {
+                                manifest.record(&from, &to);
+                            }
+                        }
+                    }
+                    (CullAction::Delete, true) => {
+                        for file in std::iter::once(dup).chain(companions) {
+                            println!("   🗑️  [dry-run] DELETE {}", file.display());
+                        }
+                    }
+                    (CullAction::Delete, false) => {
+                        for file in std::iter::once(dup).chain(companions) {
+                            fs::remove_file(file)
+                                .with_context(|| format!("Failed to delete {}", file.display()))?;
+                            println!("   🗑️  Deleted {}", file.display());
+                        }
+                    }
+                    (_, true) => println!(
+                        "   🔗 [dry-run] {} {} → {}",
+                        if action == CullAction::Hardlink {
+                            "HARDLINK"
+                        } else {
+                            "SYMLINK"
+                        },
+                        dup.display(),
+                        group[0].display()
+                    ),
+                    (_, false) if same_file(&group[0], dup)? => {
+                        println!("   🔗 {} is already linked to the keeper", dup.display());
+                    }
+                    (_, false) => {
+                        let freed = replace_with_link(&group[0], dup, action)?;
+                        reclaimed += freed;
+                        println!(
+                            "   🔗 Linked {} → {} ({} reclaimed)",
+                            dup.display(),
+                            group[0].display(),
+                            format_bytes(freed)
+                        );
+                    }
+                }
+                if action.is_link() {
+                    for raw in companions {
+                        println!("   ℹ️  RAW pair {} left in place", raw.display());
+                    }
+                }
+            }
+
+            if let Some(out) = history_out.as_mut() {
+                let record = CullHistoryRecord {
+                    timestamp: Utc::now().to_rfc3339(),
+                    retained,
+                    culled: culled_paths,
+                    action: action.history_label().to_string(),
+                };
+                writeln!(out, "{}", serde_json::to_string(&record)?)?;
+            }
+        }
+        Ok(())
+    })();
+
+    if let Some(manifest) = manifest.filter(|m| !m.moves.is_empty()) {
+        let manifest_path = manifest.save(target_dir)?;
+        println!(
+            "\n↩️  Undo these moves with: cullrs undo --manifest {}",
+            manifest_path.display()
+        );
+    }
+    result?;
+
+    if action.is_link() && !dry_run {
+        println!("\n♻️  Reclaimed {}", format_bytes(reclaimed));
+    }
+    if dry_run {
+        println!("\n⚠️  Dry-run only; no files were changed.");
+    } else {
+        println!("\n✅ Recorded cull history in {}", history_file.display());
+    }
+    Ok(())
+}
+
+// Renames `from` to `to`, falling back to copy, fsync and remove when they are
+// on different filesystems (e.g. an absolute duplicates_dir on another disk).
+// The modification time is carried over so timestamp strategies still apply.
+fn move_file(from: &Path, to: &Path) -> io::Result<()> {
+    match fs::rename(from, to) {
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {}
+        result => return result,
+    }
+    let copied = (|| -> io::Result<()> {
+        let modified = fs::metadata(from)?.modified()?;
+        fs::copy(from, to)?;
+        let file = OpenOptions::new().write(true).open(to)?;
+        file.set_modified(modified)?;
+        file.sync_all()
+    })();
+    // Only remove the source once the copy is safely on disk
+    if let Err(e) = copied.and_then(|()| fs::remove_file(from)) {
+        let _ = fs::remove_file(to);
+        return Err(e);
+    }
     Ok(())
 }
 
+// Moves `dup` and its RAW companions into `target_dir` as one unit: if any
+// of them fails, the ones already moved are put back. Returns (from, to) pairs.
+fn move_with_companions(
+    dup: &Path,
+    companions: &[PathBuf],
+    target_dir: &Path,
+) -> Result<Vec<(PathBuf, PathBuf)>> {
+    let mut moved: Vec<(PathBuf, PathBuf)> = Vec::new();
+    let result = (|| -> Result<()> {
+        for file in std::iter::once(dup).chain(companions.iter().map(PathBuf::as_path)) {
+            let dest = get_unique_destination(target_dir, file)?;
+            if let Err(e) = move_file(file, &dest) {
+                let _ = fs::remove_file(&dest);
+                return Err(e).with_context(|| format!("Failed to move {:?} → {:?}", file, dest));
+            }
+            moved.push((file.to_path_buf(), dest));
+        }
+        Ok(())
+    })();
+    if let Err(e) = result {
+        for (from, to) in moved.iter().rev() {
+            let _ = move_file(to, from);
+        }
+        return Err(e);
+    }
+    Ok(moved)
+}
+
+// Size and content hash for each file of a group, in the group's order
+fn describe_plan_files(files: &[PathBuf]) -> Result<Vec<PlanFile>> {
+    files
+        .par_iter()
+        .map(|path| {
+            let size = fs::metadata(path)
+                .with_context(|| format!("Failed to stat {:?}", path))?
+                .len();
+            Ok(PlanFile {
+                path: path.clone(),
+                size,
+                content_hash: content_hash(path)?.to_hex().to_string(),
+            })
+        })
+        .collect()
+}
+
+fn write_plan_csv(plan: &CullPlan, out: impl Write) -> Result<()> {
+    let mut writer = csv::Writer::from_writer(out);
+    for group in &plan.groups {
+        for (i, file) in group.files.iter().enumerate() {
+            let role = if i == 0 {
+                "keep"
+            } else if group.raw_pairs.iter().any(|pair| pair.raw == file.path) {
+                "raw_pair"
+            } else {
+                "cull"
+            };
+            writer.serialize(ScanCsvRow {
+                group_id: group.id,
+                confidence: group.confidence,
+                max_distance: group.max_distance,
+                role,
+                path: &file.path,
+                size: file.size,
+                content_hash: &file.content_hash,
+            })?;
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+// Atomically replaces `dup` with a hard or symbolic link to `keeper` by
+// creating the link under a temporary name and renaming it over `dup`.
+// Returns the bytes freed: nothing if `dup` is itself a link or still has
+// other hard links.
+fn replace_with_link(keeper: &Path, dup: &Path, action: CullAction) -> Result<u64> {
+    if is_symlink(keeper) {
+        anyhow::bail!(
+            "Refusing to link {:?} to {:?}, which is a symlink",
+            dup,
+            keeper
+        );
+    }
+    if same_file(keeper, dup)? {
+        anyhow::bail!(
+            "Refusing to link {:?} to {:?}: they are the same file",
+            dup,
+            keeper
+        );
+    }
+    let meta = fs::symlink_metadata(dup).with_context(|| format!("Failed to stat {:?}", dup))?;
+    let freed = if meta.file_type().is_symlink() || hard_link_count(&meta) > 1 {
+        0
+    } else {
+        meta.len()
+    };
+
+    let file_name = dup.file_name().unwrap().to_string_lossy();
+    let temp = dup.with_file_name(format!(".{}.cullrs-link", file_name));
+    let _ = fs::remove_file(&temp);
+    match action {
+        CullAction::Hardlink => fs::hard_link(keeper, &temp),
+        CullAction::Symlink => {
+            // Absolute so the link keeps working if the scan root was relative
+            let target = std::path::absolute(keeper)?;
+            #[cfg(unix)]
+            let linked = std::os::unix::fs::symlink(&target, &temp);
+            #[cfg(windows)]
+            let linked = std::os::windows::fs::symlink_file(&target, &temp);
+            linked
+        }
+        _ => unreachable!("replace_with_link called for {:?}", action),
+    }
+    .with_context(|| format!("Failed to link {:?} → {:?}", temp, keeper))?;
+
+    if let Err(e) = fs::rename(&temp, dup) {
+        let _ = fs::remove_file(&temp);
+        return Err(e).with_context(|| format!("Failed to replace {:?}", dup));
+    }
+    Ok(freed)
+}
+
+fn is_symlink(path: &Path) -> bool {
+    fs::symlink_metadata(path).is_ok_and(|meta| meta.file_type().is_symlink())
+}
+
+#[cfg(unix)]
+fn same_filesystem(a: &Path, b: &Path) -> Result<bool> {
+    use std::os::unix::fs::MetadataExt;
+    let dev = |p: &Path| {
+        fs::metadata(p)
+            .map(|m| m.dev())
+            .with_context(|| format!("Failed to stat {:?}", p))
+    };
+    Ok(dev(a)? == dev(b)?)
+}
+
+// Without device numbers, let fs::hard_link report a cross-device error
+#[cfg(not(unix))]
+fn same_filesystem(_a: &Path, _b: &Path) -> Result<bool> {
+    Ok(true)
+}
+
+#[cfg(unix)]
+fn same_file(a: &Path, b: &Path) -> Result<bool> {
+    use std::os::unix::fs::MetadataExt;
+    let id = |p: &Path| {
+        fs::metadata(p)
+            .map(|m| (m.dev(), m.ino()))
+            .with_context(|| format!("Failed to stat {:?}", p))
+    };
+    Ok(id(a)? == id(b)?)
+}
+
+#[cfg(not(unix))]
+fn same_file(_a: &Path, _b: &Path) -> Result<bool> {
+    Ok(false)
+}
+
+#[cfg(unix)]
+fn hard_link_count(meta: &fs::Metadata) -> u64 {
+    std::os::unix::fs::MetadataExt::nlink(meta)
+}
+
+#[cfg(not(unix))]
+fn hard_link_count(_meta: &fs::Metadata) -> u64 {
+    1
+}
+
+// Turns a plan into keeper-first groups plus the RAW pairs culled with them,
+// skipping entries whose files are gone and refusing plans that would cull a
+// file another group keeps, or cull the same file twice.
+fn validate_plan(plan: &CullPlan) -> Result<(Vec<Vec<PathBuf>>, RawPairs)> {
+    let keepers: HashSet<&PathBuf> = plan.groups.iter().map(|g| &g.keep).collect();
+    // Culled path → id of the first group that culls it
+    let mut culled: HashMap<&PathBuf, usize> = HashMap::new();
+    let mut groups = Vec::new();
+    let mut raw_pairs = RawPairs::new();
+
+    for group in &plan.groups {
+        let raws = group.raw_pairs.iter().map(|pair| &pair.raw);
+        for path in group.cull.iter().chain(raws) {
+            if keepers.contains(path) {
+                anyhow::bail!(
+                    "Group {} culls {:?}, which is a keeper in this plan",
+                    group.id,
+                    path
+                );
+            }
+            if let Some(first) = culled.insert(path, group.id) {
+                anyhow::bail!(
+                    "Group {} culls {:?}, which group {} already culls",
+                    group.id,
+                    path,
+                    first
+                );
+            }
+        }
+        if let Some(pair) = group
+            .raw_pairs
+            .iter()
+            .find(|pair| !group.cull.contains(&pair.jpeg))
+        {
+            anyhow::bail!(
+                "Group {} pairs {:?} with {:?}, which it does not cull",
+                group.id,
+                pair.raw,
+                pair.jpeg
+            );
+        }
+        if !group.keep.is_file() {
+            eprintln!(
+                "⚠️  Group {}: keeper {:?} is missing; skipping group",
+                group.id, group.keep
+            );
+            continue;
+        }
+
+        let mut files = vec![group.keep.clone()];
+        for path in &group.cull {
+            if path.is_file() {
+                files.push(path.clone());
+            } else {
+                eprintln!(
+                    "⚠️  Group {}: {:?} is missing; skipping file",
+                    group.id, path
+                );
+            }
+        }
+        for pair in &group.raw_pairs {
+            if !files.contains(&pair.jpeg) {
+                continue;
+            }
+            if pair.raw.is_file() {
+                raw_pairs
+                    .entry(pair.jpeg.clone())
+                    .or_default()
+                    .push(pair.raw.clone());
+            } else {
+                eprintln!(
+                    "⚠️  Group {}: {:?} is missing; skipping file",
+                    group.id, pair.raw
+                );
+            }
+        }
+        if files.len() > 1 {
+            groups.push(files);
+        }
+    }
+    Ok((groups, raw_pairs))
+}
+
 fn handle_history_command(command: HistoryCmd) -> Result<()> {
+    let config = load_config(&get_config_path()?).unwrap_or_default();
+
     match command {
         HistoryCmd::List { path } => {
             let history_file = path.join(".history.jsonl");
@@ -439,7 +1795,15 @@ fn handle_history_command(command: HistoryCmd) -> Result<()> {
                 match serde_json::from_str::<CullHistoryRecord>(&line) {
                     Ok(rec) => println!(
                         "[{}] {}\n     kept: {}\n     culled: {:?}\n     action: {}\n",
-                        i, rec.timestamp, rec.retained, rec.culled, rec.action
+                        i,
+                        rec.timestamp,
+                        if rec.retained.is_empty() {
+                            "-"
+                        } else {
+                            &rec.retained
+                        },
+                        rec.culled,
+                        rec.action
                     ),
                     Err(err) => eprintln!("⚠️  Skipping malformed entry {}: {}", i, err),
                 }
@@ -455,10 +1819,10 @@ fn handle_history_command(command: HistoryCmd) -> Result<()> {
             let mut stored: Vec<(CullHistoryRecord, String)> = Vec::new();
             for line in reader.lines() {
                 let line = line?;
-                if let Ok(rec) = serde_json::from_str::<CullHistoryRecord>(&line) {
-                    if rec.action == "moved" {
-                        stored.push((rec, line));
-                    }
+                if let Ok(rec) = serde_json::from_str::<CullHistoryRecord>(&line)
+                    && rec.action == "moved"
+                {
+                    stored.push((rec, line));
                 }
             }
 
@@ -489,7 +1853,7 @@ fn handle_history_command(command: HistoryCmd) -> Result<()> {
                 );
                 for orig in &rec.culled {
                     let fname = Path::new(orig).file_name().unwrap_or_default();
-                    let src = path.join("duplicates").join(&fname);
+                    let src = path.join(&config.duplicates_dir).join(fname);
                     let dest = Path::new(orig);
 
                     if !src.exists() {
@@ -500,7 +1864,7 @@ fn handle_history_command(command: HistoryCmd) -> Result<()> {
                         eprintln!("⚠️ Source and destination are the same; skipping {:?}", src);
                         continue;
                     }
-                    fs::rename(&src, &dest)
+                    move_file(&src, dest)
                         .with_context(|| format!("Failed to restore {:?} → {:?}", src, dest))?;
                     println!("🔄 Restored {:?} → {:?}", src, dest);
                 }
@@ -529,51 +1893,567 @@ fn handle_history_command(command: HistoryCmd) -> Result<()> {
     Ok(())
 }
 
-// Enhanced image detection using file headers when possible
-fn is_image_file(path: &Path) -> bool {
-    // First try to read the file header to detect image type
-    if let Ok(mut file) = File::open(path) {
-        let mut buffer = [0; 12];
-        if file.read(&mut buffer).is_ok() {
-            // Check for common image file signatures
-            if buffer.starts_with(&[0xFF, 0xD8, 0xFF]) {
-                // JPEG
-                return true;
+// Moves files back in reverse order. Entries that cannot be restored (the
+// moved file is gone, or something now occupies the original path) are kept
+// in the manifest; it is deleted once everything is back.
+fn handle_undo_command(manifest_path: &Path, dry_run: bool) -> Result<()> {
+    let mut manifest = MoveManifest::load(manifest_path)?;
+    println!(
+        "↩️  Undoing {} move(s) from {}",
+        manifest.moves.len(),
+        manifest.created
+    );
+
+    let mut remaining = Vec::new();
+    for entry in manifest.moves.into_iter().rev() {
+        if !entry.to.exists() {
+            eprintln!("⚠️ {:?} no longer exists; skipping", entry.to);
+            remaining.push(entry);
+            continue;
+        }
+        if entry.from.exists() {
+            eprintln!("⚠️ {:?} is occupied; skipping", entry.from);
+            remaining.push(entry);
+            continue;
+        }
+        if dry_run {
+            println!(
+                "   [dry-run] RESTORE {} → {}",
+                entry.to.display(),
+                entry.from.display()
+            );
+            continue;
+        }
+        if let Some(parent) = entry.from.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create directory {:?}", parent))?;
+        }
+        move_file(&entry.to, &entry.from)
+            .with_context(|| format!("Failed to restore {:?} → {:?}", entry.to, entry.from))?;
+        println!(
+            "🔄 Restored {} → {}",
+            entry.to.display(),
+            entry.from.display()
+        );
+    }
+
+    if dry_run {
+        println!("\n⚠️  Dry-run only; no files were changed.");
+    } else if remaining.is_empty() {
+        fs::remove_file(manifest_path)
+            .with_context(|| format!("Failed to remove manifest {:?}", manifest_path))?;
+        println!("\n✅ All moves undone; removed {}", manifest_path.display());
+    } else {
+        remaining.reverse();
+        println!(
+            "\n⚠️  {} move(s) could not be undone; kept in {}",
+            remaining.len(),
+            manifest_path.display()
+        );
+        manifest.moves = remaining;
+        manifest.write(manifest_path)?;
+    }
+    Ok(())
+}
+
+fn handle_hash_command(files: &[PathBuf], perceptual: bool) -> Result<()> {
+    let hasher = perceptual_hasher();
+    let mut results = Vec::new();
+
+    for path in files {
+        let content = content_hash(path)?;
+        println!("▶ {}", path.display());
+        println!("   blake3:     {}", content.to_hex());
+
+        let phash = if perceptual {
+            match sniff_image_format(path) {
+                Some(format) => {
+                    let image = DiscoveredImage {
+                        path: path.clone(),
+                        format,
+                    };
+                    match hash_image_file(&hasher, &image, None) {
+                        Ok(hash) => {
+                            println!("   perceptual: {:016x}", hash);
+                            Some(hash)
+                        }
+                        Err(err) => {
+                            println!("   perceptual: ⚠️  {:#}", err);
+                            None
+                        }
+                    }
+                }
+                None => {
+                    println!("   perceptual: not an image");
+                    None
+                }
             }
-            if buffer.starts_with(&[0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A]) {
-                // PNG
-                return true;
+        } else {
+            None
+        };
+        results.push((path, content, phash));
+    }
+
+    if results.len() > 1 {
+        println!("\nPairwise comparison:");
+        for (i, (path_a, content_a, phash_a)) in results.iter().enumerate() {
+            for (path_b, content_b, phash_b) in &results[i + 1..] {
+                let content = if content_a == content_b {
+                    "identical content"
+                } else {
+                    "different content"
+                };
+                match (phash_a, phash_b) {
+                    (Some(a), Some(b)) => println!(
+                        "   {} ↔ {}: {}, distance {}",
+                        path_a.display(),
+                        path_b.display(),
+                        content,
+                        hamming_distance(*a, *b)
+                    ),
+                    _ => println!(
+                        "   {} ↔ {}: {}",
+                        path_a.display(),
+                        path_b.display(),
+                        content
+                    ),
+                }
             }
-            if buffer.starts_with(&[0x47, 0x49, 0x46, 0x38]) {
-                // GIF
-                return true;
+        }
+    }
+    Ok(())
+}
+
+fn handle_diff_command(
+    dir_a: &Path,
+    dir_b: &Path,
+    perceptual: bool,
+    threshold: Option<u32>,
+) -> Result<()> {
+    validate_directory(dir_a)?;
+    validate_directory(dir_b)?;
+    let config = load_config(&get_config_path()?).unwrap_or_default();
+
+    println!("▶ Comparing {} ↔ {}", dir_a.display(), dir_b.display());
+    let images_a = scan_directory(dir_a, &ScanFilters::default(), false)?.images;
+    let images_b = scan_directory(dir_b, &ScanFilters::default(), false)?.images;
+    // Only images sharing a size and partial hash with another image (on
+    // either side) get fully hashed; the rest cannot have an identical match.
+    let both: Vec<&Path> = images_a
+        .iter()
+        .chain(&images_b)
+        .map(|image| image.path.as_path())
+        .collect();
+    let hashes = staged_content_hashes(&both)?;
+
+    let mut by_hash_b: HashMap<blake3::Hash, Vec<&DiscoveredImage>> = HashMap::new();
+    for image in &images_b {
+        if let Some(hash) = hashes.get(image.path.as_path()) {
+            by_hash_b.entry(*hash).or_default().push(image);
+        }
+    }
+
+    let mut matched = Vec::new();
+    let mut only_a = Vec::new();
+    let mut matched_b = HashSet::new();
+    for image in &images_a {
+        match hashes
+            .get(image.path.as_path())
+            .and_then(|hash| by_hash_b.get(hash))
+        {
+            Some(others) => {
+                matched.push((image, others[0]));
+                matched_b.extend(others.iter().map(|other| &other.path));
             }
-            if buffer.starts_with(&[0x42, 0x4D]) {
-                // BMP
-                return true;
+            None => only_a.push(image),
+        }
+    }
+    let mut only_b: Vec<&DiscoveredImage> = images_b
+        .iter()
+        .filter(|image| !matched_b.contains(&image.path))
+        .collect();
+
+    let mut similar = Vec::new();
+    if perceptual && !only_a.is_empty() && !only_b.is_empty() {
+        let threshold = threshold.unwrap_or(config.duplicates_hash_threshold);
+        let hasher = perceptual_hasher();
+        let phash = |images: &[&DiscoveredImage]| -> Vec<Option<u64>> {
+            images
+                .par_iter()
+                .map(|image| hash_image_file(&hasher, image, None).ok())
+                .collect()
+        };
+        let phash_a = phash(&only_a);
+        let phash_b = phash(&only_b);
+
+        // Index positions of `only_b` images that hashed successfully
+        let hashed_b: Vec<(usize, u64)> = phash_b
+            .iter()
+            .enumerate()
+            .filter_map(|(j, hash)| hash.map(|h| (j, h)))
+            .collect();
+        let index = SimilarityIndex::from_hashes(hashed_b.iter().map(|(_, hash)| *hash));
+
+        let mut used_a = vec![false; only_a.len()];
+        let mut used_b = vec![false; only_b.len()];
+        for (i, hash_a) in phash_a.iter().enumerate() {
+            let Some(hash_a) = hash_a else { continue };
+            let closest = index
+                .query(*hash_a, threshold)
+                .into_iter()
+                .map(|(k, distance)| (hashed_b[k].0, distance))
+                .filter(|(j, _)| !used_b[*j])
+                .min_by_key(|(_, distance)| *distance);
+            if let Some((j, distance)) = closest {
+                used_a[i] = true;
+                used_b[j] = true;
+                similar.push((only_a[i], only_b[j], distance));
             }
-            if buffer.starts_with(&[0x49, 0x49, 0x2A, 0x00])
-                || buffer.starts_with(&[0x4D, 0x4D, 0x00, 0x2A])
-            {
-                // TIFF
-                return true;
+        }
+        only_a = only_a
+            .into_iter()
+            .zip(used_a)
+            .filter_map(|(image, used)| (!used).then_some(image))
+            .collect();
+        only_b = only_b
+            .into_iter()
+            .zip(used_b)
+            .filter_map(|(image, used)| (!used).then_some(image))
+            .collect();
+    }
+
+    println!("\n✅ Identical content: {}", matched.len());
+    for (a, b) in &matched {
+        println!("   {} ↔ {}", a.path.display(), b.path.display());
+    }
+    if perceptual {
+        println!("\n🔎 Visually similar: {}", similar.len());
+        for (a, b, distance) in &similar {
+            println!(
+                "   {} ↔ {} (distance {})",
+                a.path.display(),
+                b.path.display(),
+                distance
+            );
+        }
+    }
+    println!("\n◀ Only in {}: {}", dir_a.display(), only_a.len());
+    for image in &only_a {
+        println!("   {}", image.path.display());
+    }
+    println!("\n▶ Only in {}: {}", dir_b.display(), only_b.len());
+    for image in &only_b {
+        println!("   {}", image.path.display());
+    }
+    Ok(())
+}
+
+fn handle_archive_command(
+    archive: &Path,
+    library: &Path,
+    perceptual: bool,
+    threshold: Option<u32>,
+) -> Result<()> {
+    validate_directory(library)?;
+    let config = load_config(&get_config_path()?).unwrap_or_default();
+
+    println!("▶ Comparing {} ↔ {}", archive.display(), library.display());
+    let entries = read_archive_images(archive, perceptual)?;
+    let images = scan_directory(library, &ScanFilters::default(), false)?.images;
+    let hashes = content_hash_all(&images)?;
+
+    let mut by_hash: HashMap<blake3::Hash, &DiscoveredImage> = HashMap::new();
+    for (hash, image) in &hashes {
+        by_hash.entry(*hash).or_insert(image);
+    }
+
+    let mut matched = Vec::new();
+    let mut unmatched = Vec::new();
+    for entry in &entries {
+        match by_hash.get(&entry.content) {
+            Some(image) => matched.push((entry, *image)),
+            None => unmatched.push(entry),
+        }
+    }
+
+    let mut similar = Vec::new();
+    if perceptual && !unmatched.is_empty() && !images.is_empty() {
+        let threshold = threshold.unwrap_or(config.duplicates_hash_threshold);
+        let hasher = perceptual_hasher();
+        let library_hashes: Vec<(u64, &DiscoveredImage)> = images
+            .par_iter()
+            .filter_map(|image| {
+                hash_image_file(&hasher, image, None)
+                    .ok()
+                    .map(|hash| (hash, image))
+            })
+            .collect();
+        let index = SimilarityIndex::from_hashes(library_hashes.iter().map(|(hash, _)| *hash));
+
+        unmatched.retain(|entry| {
+            let closest = entry.perceptual.and_then(|hash| {
+                index
+                    .query(hash, threshold)
+                    .into_iter()
+                    .min_by_key(|(_, distance)| *distance)
+            });
+            match closest {
+                Some((k, distance)) => {
+                    similar.push((*entry, library_hashes[k].1, distance));
+                    false
+                }
+                None => true,
             }
+        });
+    }
+
+    println!("\n✅ Already in library: {}", matched.len());
+    for (entry, image) in &matched {
+        println!("   {} ↔ {}", entry.name, image.path.display());
+    }
+    if perceptual {
+        println!("\n🔎 Visually similar: {}", similar.len());
+        for (entry, image, distance) in &similar {
+            println!(
+                "   {} ↔ {} (distance {})",
+                entry.name,
+                image.path.display(),
+                distance
+            );
         }
     }
+    println!("\n🆕 Not in library: {}", unmatched.len());
+    for entry in &unmatched {
+        println!("   {}", entry.name);
+    }
+    Ok(())
+}
 
-    // Fallback to extension check
-    let allowed_exts = [
-        "jpg", "jpeg", "png", "gif", "bmp", "tiff", "webp", "raw", "cr2", "nef", "arw",
-    ];
-    if let Some(ext) = path.extension().and_then(|s| s.to_str()) {
-        allowed_exts.contains(&ext.to_lowercase().as_str())
-    } else {
-        false
+/// An image read from inside a ZIP archive
+struct ArchiveImage {
+    /// Entry name within the archive
+    name: String,
+    content: blake3::Hash,
+    perceptual: Option<u64>,
+}
+
+// Archive entries larger than this are skipped rather than decompressed
+const ARCHIVE_ENTRY_MAX_BYTES: u64 = 256 * 1024 * 1024;
+
+// Streams archive entries one at a time, keeping only their hashes. Entries
+// are recognised the same way discovery does: by sniffed content, above the
+// degenerate size, whatever their name. Only --perceptual buffers an entry,
+// since decoding needs the whole image in memory.
+fn read_archive_images(archive: &Path, perceptual: bool) -> Result<Vec<ArchiveImage>> {
+    let file = File::open(archive).with_context(|| format!("Failed to open {:?}", archive))?;
+    let mut zip = zip::ZipArchive::new(BufReader::new(file))
+        .with_context(|| format!("Failed to read ZIP archive {:?}", archive))?;
+    let hasher = perceptual.then(perceptual_hasher);
+
+    let pb = ProgressBar::new(zip.len() as u64);
+    pb.set_style(ProgressStyle::with_template(
+        "{bar:40.cyan/blue} {pos:>7}/{len:7} {msg} [{elapsed_precise}]",
+    )?);
+    pb.set_message("Reading archive");
+
+    let mut images = Vec::new();
+    let mut oversized = 0;
+    for i in 0..zip.len() {
+        pb.inc(1);
+        let entry = zip
+            .by_index(i)
+            .with_context(|| format!("Failed to read entry {} of {:?}", i, archive))?;
+        if !entry.is_file() || entry.size() < DEGENERATE_FILE_SIZE {
+            continue;
+        }
+        if entry.size() > ARCHIVE_ENTRY_MAX_BYTES {
+            oversized += 1;
+            continue;
+        }
+        let name = entry.name().to_string();
+        let extract_error = || format!("Failed to extract {} from {:?}", name, archive);
+
+        // The declared size can lie, so the read itself is capped too
+        let mut reader = entry.take(ARCHIVE_ENTRY_MAX_BYTES);
+        let mut header = Vec::with_capacity(12);
+        (&mut reader)
+            .take(12)
+            .read_to_end(&mut header)
+            .with_context(extract_error)?;
+        let Some(format) = image_format_from_magic(&header) else {
+            continue;
+        };
+        let mut reader = Cursor::new(header).chain(reader);
+
+        let (content, perceptual) = match &hasher {
+            Some(hasher) => {
+                let mut bytes = Vec::new();
+                reader.read_to_end(&mut bytes).with_context(extract_error)?;
+                let perceptual =
+                    hash_image_bytes(hasher, &bytes, format, &archive.join(&name)).ok();
+                (blake3::hash(&bytes), perceptual)
+            }
+            None => {
+                let mut content = blake3::Hasher::new();
+                content.update_reader(reader).with_context(extract_error)?;
+                (content.finalize(), None)
+            }
+        };
+        images.push(ArchiveImage {
+            content,
+            name,
+            perceptual,
+        });
+    }
+    pb.finish_and_clear();
+    if oversized > 0 {
+        eprintln!(
+            "⚠️  Skipped {} archive file(s) over {}",
+            oversized,
+            format_bytes(ARCHIVE_ENTRY_MAX_BYTES)
+        );
     }
+    Ok(images)
 }
 
-fn scan_directory(dir: &Path) -> Result<Vec<PathBuf>> {
+/// An image found during discovery. The format comes from the file's content,
+/// not its extension, so renamed or extensionless files are handled correctly.
+#[derive(Debug, Clone)]
+struct DiscoveredImage {
+    path: PathBuf,
+    format: ImageFormat,
+}
+
+// Detect the image format from file headers, ignoring the extension
+fn sniff_image_format(path: &Path) -> Option<ImageFormat> {
+    let mut file = File::open(path).ok()?;
+    let mut buffer = [0; 12];
+    let read = file.read(&mut buffer).ok()?;
+    image_format_from_magic(&buffer[..read])
+}
+
+fn image_format_from_magic(buffer: &[u8]) -> Option<ImageFormat> {
+    // Check for common image file signatures
+    if buffer.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        return Some(ImageFormat::Jpeg);
+    }
+    if buffer.starts_with(&[0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A]) {
+        return Some(ImageFormat::Png);
+    }
+    if buffer.starts_with(&[0x47, 0x49, 0x46, 0x38]) {
+        return Some(ImageFormat::Gif);
+    }
+    if buffer.starts_with(&[0x42, 0x4D]) {
+        return Some(ImageFormat::Bmp);
+    }
+    if buffer.starts_with(&[0x49, 0x49, 0x2A, 0x00])
+        || buffer.starts_with(&[0x4D, 0x4D, 0x00, 0x2A])
+    {
+        // TIFF, which also covers TIFF-based RAW files (CR2, NEF, ARW, DNG)
+        return Some(ImageFormat::Tiff);
+    }
+    if buffer.len() >= 12 && buffer.starts_with(b"RIFF") && &buffer[8..12] == b"WEBP" {
+        return Some(ImageFormat::WebP);
+    }
+    None
+}
+
+/// Files found by a directory walk, split into hashable images and
+/// degenerate files that are too small to be real photos.
+struct Discovery {
+    images: Vec<DiscoveredImage>,
+    /// Matched by exact content only; there is no video decoder to hash frames
+    videos: Vec<PathBuf>,
+    degenerate: Vec<PathBuf>,
+}
+
+// Image and video files below this size are set aside instead of being hashed
+const DEGENERATE_FILE_SIZE: u64 = 1024;
+
+/// How discovery treats a single file.
+enum FileKind {
+    Image(ImageFormat),
+    Video,
+    Degenerate,
+    /// Left out by a glob, size limit, file type or minimum dimension
+    Filtered,
+    /// Neither an image nor a video
+    Other,
+}
+
+// Regular files under `dir` that survive the exclusions, with their metadata.
+// A symlink is not a second copy of its target: grouping the two could cull
+// the real file and keep the link, so links are never walked.
+fn walk_files<'a>(
+    dir: &'a Path,
+    config: &'a Config,
+    excluded_globs: &'a [glob::Pattern],
+) -> impl Iterator<Item = (walkdir::DirEntry, fs::Metadata)> + 'a {
+    WalkDir::new(dir)
+        .into_iter()
+        .filter_entry(move |e| !is_excluded(e, dir, config, excluded_globs))
+        .filter_map(Result::ok)
+        .filter(|entry| !entry.path_is_symlink())
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            metadata.is_file().then_some((entry, metadata))
+        })
+}
+
+// Discovery's rules for one file, shared by scans and estimates
+fn classify_file(
+    dir: &Path,
+    path: &Path,
+    size: u64,
+    config: &Config,
+    filters: &ScanFilters,
+) -> FileKind {
+    if !filters.includes(dir, path) {
+        return FileKind::Filtered;
+    }
+    if size < DEGENERATE_FILE_SIZE {
+        return if has_image_extension(path)
+            || has_video_extension(path)
+            || sniff_image_format(path).is_some()
+        {
+            FileKind::Degenerate
+        } else {
+            FileKind::Other
+        };
+    }
+    if !filters.size_in_range(size)
+        || config
+            .scan_max_file_size
+            .is_some_and(|max_size| size > max_size)
+    {
+        return FileKind::Filtered;
+    }
+    if let Some(format) = sniff_image_format(path) {
+        if !is_wanted_type(format, config) {
+            return FileKind::Filtered;
+        }
+        if let Some(min_dim) = config.scan_min_dimension
+            && is_below_min_dimension(path, format, min_dim)
+        {
+            return FileKind::Filtered;
+        }
+        return FileKind::Image(format);
+    }
+    if has_video_extension(path) && is_video(path) {
+        return if is_wanted_extension(path, config) {
+            FileKind::Video
+        } else {
+            FileKind::Filtered
+        };
+    }
+    FileKind::Other
+}
+
+// `force` skips the soft-limit prompt, like --force on the commands that cull
+fn scan_directory(dir: &Path, filters: &ScanFilters, force: bool) -> Result<Discovery> {
     let config = load_config(&get_config_path()?).unwrap_or_default();
+    let mut excluded_globs = compile_globs(&config.excluded_globs)?;
+    excluded_globs.extend(filters.exclude.iter().cloned());
 
     let pb = ProgressBar::new_spinner();
     pb.set_style(ProgressStyle::with_template(
@@ -583,135 +2463,1177 @@ fn scan_directory(dir: &Path) -> Result<Vec<PathBuf>> {
     pb.enable_steady_tick(Duration::from_millis(100));
 
     let mut images = Vec::new();
+    let mut videos = Vec::new();
+    let mut degenerate = Vec::new();
     let mut file_count = 0;
+    let mut filtered_count = 0;
+
+    for (entry, metadata) in walk_files(dir, &config, &excluded_globs) {
+        let path = entry.path();
+        file_count += 1;
+        match classify_file(dir, path, metadata.len(), &config, filters) {
+            FileKind::Image(format) => images.push(DiscoveredImage {
+                path: path.to_path_buf(),
+                format,
+            }),
+            FileKind::Video => videos.push(path.to_path_buf()),
+            FileKind::Degenerate => degenerate.push(path.to_path_buf()),
+            FileKind::Filtered => filtered_count += 1,
+            FileKind::Other => {}
+        }
+
+        if file_count % 100 == 0 {
+            pb.set_message(format!(
+                "Scanned {} files, found {} images",
+                file_count,
+                images.len()
+            ));
+        }
+        pb.tick();
+    }
 
-    for entry in WalkDir::new(dir)
+    let paired = remove_paired_raws(&mut images);
+    if paired > 0 {
+        eprintln!(
+            "▶ Paired {} RAW file(s) with their JPEGs; they follow the JPEG when culled",
+            paired
+        );
+    }
+
+    pb.finish_with_message(format!(
+        "Scan complete: {} images and {} videos found from {} files ({} skipped by filters, {} degenerate)",
+        images.len(),
+        videos.len(),
+        file_count,
+        filtered_count,
+        degenerate.len()
+    ));
+
+    let limit = config.scan_soft_limit;
+    if limit > 0 && images.len() as u64 > limit && !force && !config.auto_confirm {
+        confirm_huge_scan(&images, limit)?;
+    }
+    Ok(Discovery {
+        images,
+        videos,
+        degenerate,
+    })
+}
+
+// Asks before starting a scan above the soft limit. The prompt goes to stderr
+// so it never ends up in redirected output such as a JSON plan.
+fn confirm_huge_scan(images: &[DiscoveredImage], limit: u64) -> Result<()> {
+    eprintln!(
+        "⚠️  Found {} images, more than the soft limit of {}.",
+        images.len(),
+        limit
+    );
+    match estimate_hashing_time(images) {
+        (Some(eta), sampled) => eprintln!(
+            "   Estimated hashing time: ~{} (sampled {} images)",
+            format_duration(eta),
+            sampled
+        ),
+        (None, _) => eprintln!("   No decodable images sampled; cannot estimate time."),
+    }
+    eprint!("Continue anyway? [y/N]: ");
+    io::stderr().flush()?;
+
+    let mut input = String::new();
+    io::stdin().read_line(&mut input)?;
+    let input = input.trim().to_lowercase();
+    if input != "y" && input != "yes" {
+        anyhow::bail!(
+            "Scan cancelled; raise the limit with `cullrs config set --soft-limit <N>` (0 = no limit)"
+        );
+    }
+    Ok(())
+}
+
+/// Per-run filters from `--exclude`/`--include` and `--min-size`/`--max-size`,
+/// applied on top of the configured ones. Patterns match paths relative to the
+/// scanned directory.
+#[derive(Default)]
+struct ScanFilters {
+    exclude: Vec<glob::Pattern>,
+    include: Vec<glob::Pattern>,
+    min_size: Option<u64>,
+    max_size: Option<u64>,
+}
+
+impl ScanFilters {
+    fn size_in_range(&self, size: u64) -> bool {
+        self.min_size.is_none_or(|min| size >= min) && self.max_size.is_none_or(|max| size <= max)
+    }
+
+    // Files must match an include pattern when any are given
+    fn includes(&self, root: &Path, path: &Path) -> bool {
+        self.include.is_empty()
+            || path.strip_prefix(root).is_ok_and(|relative| {
+                self.include
+                    .iter()
+                    .any(|pattern| pattern.matches_path(relative))
+            })
+    }
+}
+
+// Parses sizes like `1024`, `500k`, `2MB` or `1.5GiB`. Units are binary
+// (1 KB = 1024 bytes), matching how sizes are printed.
+fn parse_size(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    let split = s
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("invalid size '{}'", s))?;
+    let multiplier: u64 = match unit.trim().to_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kb" | "kib" => 1 << 10,
+        "m" | "mb" | "mib" => 1 << 20,
+        "g" | "gb" | "gib" => 1 << 30,
+        "t" | "tb" | "tib" => 1 << 40,
+        other => {
+            return Err(format!(
+                "unknown size unit '{}' (use B, KB, MB, GB or TB)",
+                other
+            ));
+        }
+    };
+    Ok((number * multiplier as f64) as u64)
+}
+
+fn parse_glob(s: &str) -> Result<glob::Pattern, String> {
+    glob::Pattern::new(s).map_err(|e| format!("invalid glob '{}': {}", s, e))
+}
+
+// Zero-byte files have no header to sniff, so the extension is all we have
+fn has_image_extension(path: &Path) -> bool {
+    let allowed_exts = [
+        "jpg", "jpeg", "png", "gif", "bmp", "tiff", "webp", "raw", "cr2", "nef", "arw",
+    ];
+    path.extension()
+        .and_then(|s| s.to_str())
+        .is_some_and(|ext| allowed_exts.contains(&ext.to_lowercase().as_str()))
+}
+
+// RAW formats a camera writes next to a JPEG of the same shot
+const RAW_EXTENSIONS: [&str; 5] = ["raw", "cr2", "nef", "arw", "dng"];
+const JPEG_EXTENSIONS: [&str; 2] = ["jpg", "jpeg"];
+
+fn has_extension_in(path: &Path, exts: &[&str]) -> bool {
+    path.extension()
+        .and_then(|s| s.to_str())
+        .is_some_and(|ext| exts.contains(&ext.to_lowercase().as_str()))
+}
+
+// Same stem is not enough on its own: both files must carry the same EXIF
+// shoot time, so an unrelated file that happens to share a name never pairs
+fn is_raw_pair(jpeg: &Path, raw: &Path) -> bool {
+    read_exif_date(jpeg).is_some_and(|taken| read_exif_date(raw) == Some(taken))
+}
+
+// Existing siblings of `path` with the same stem and one of `exts`, in either case
+fn stem_siblings(path: &Path, exts: &[&str]) -> Vec<PathBuf> {
+    let mut found: Vec<PathBuf> = Vec::new();
+    for ext in exts {
+        for candidate in [
+            path.with_extension(ext),
+            path.with_extension(ext.to_uppercase()),
+        ] {
+            if candidate != path
+                && candidate.is_file()
+                && !found
+                    .iter()
+                    .any(|f| same_file(f, &candidate).unwrap_or(false))
+            {
+                found.push(candidate);
+            }
+        }
+    }
+    found
+}
+
+// RAW files paired with a JPEG, looked up on disk so a pair still moves
+// together when its RAW half was outside the scan (e.g. filtered by type)
+fn raw_companions(path: &Path) -> Vec<PathBuf> {
+    if !has_extension_in(path, &JPEG_EXTENSIONS) {
+        return Vec::new();
+    }
+    stem_siblings(path, &RAW_EXTENSIONS)
         .into_iter()
-        .filter_entry(|e| {
-            if let Some(name) = e.file_name().to_str() {
-                !config.excluded_dirs.iter().any(|excluded| name == excluded)
+        .filter(|raw| is_raw_pair(path, raw))
+        .collect()
+}
+
+/// Culled JPEGs mapped to the RAW files that are culled along with them
+type RawPairs = HashMap<PathBuf, Vec<PathBuf>>;
+
+// RAW pairs of every non-kept file in keeper-first groups. A RAW only goes
+// with its JPEG when the keeper has a RAW of its own; otherwise it may be the
+// only RAW of the shot, so it is left in place with a warning.
+fn collect_raw_pairs(groups: &[Vec<PathBuf>]) -> RawPairs {
+    let mut raw_pairs = RawPairs::new();
+    for group in groups {
+        let keeper_has_raw = !raw_companions(&group[0]).is_empty();
+        for dup in &group[1..] {
+            let raws = raw_companions(dup);
+            if raws.is_empty() {
+                continue;
+            }
+            if keeper_has_raw {
+                raw_pairs.insert(dup.clone(), raws);
             } else {
-                true
+                for raw in raws {
+                    eprintln!(
+                        "⚠️  Leaving RAW {} in place: keeper {} has no RAW pair",
+                        raw.display(),
+                        group[0].display()
+                    );
+                }
             }
+        }
+    }
+    raw_pairs
+}
+
+// Files a move or delete will touch outside the keepers, RAW pairs included
+fn cull_count(groups: &[Vec<PathBuf>], raw_pairs: &RawPairs) -> usize {
+    groups.iter().map(|g| g.len() - 1).sum::<usize>()
+        + raw_pairs.values().map(Vec::len).sum::<usize>()
+}
+
+// Drops the RAW halves of RAW+JPEG pairs from discovery: their embedded
+// preview would group them with their own JPEG. Returns how many were paired.
+fn remove_paired_raws(images: &mut Vec<DiscoveredImage>) -> usize {
+    let stem_key = |path: &Path| {
+        (
+            path.parent().map(Path::to_path_buf),
+            path.file_stem().map(|s| s.to_string_lossy().to_lowercase()),
+        )
+    };
+    let mut jpegs: HashMap<_, Vec<&Path>> = HashMap::new();
+    for image in images.iter() {
+        if has_extension_in(&image.path, &JPEG_EXTENSIONS) {
+            jpegs
+                .entry(stem_key(&image.path))
+                .or_default()
+                .push(&image.path);
+        }
+    }
+    let paired: HashSet<PathBuf> = images
+        .iter()
+        .map(|image| image.path.as_path())
+        .filter(|path| has_extension_in(path, &RAW_EXTENSIONS))
+        .filter(|raw| {
+            jpegs
+                .get(&stem_key(raw))
+                .is_some_and(|matches| matches.iter().any(|jpeg| is_raw_pair(jpeg, raw)))
         })
-        .filter_map(Result::ok)
-    {
-        let path = entry.path();
-        if path.is_file() {
-            file_count += 1;
-            if is_image_file(path) {
-                images.push(path.to_path_buf());
-            }
+        .map(Path::to_path_buf)
+        .collect();
+    images.retain(|image| !paired.contains(&image.path));
+    paired.len()
+}
+
+const VIDEO_EXTENSIONS: [&str; 4] = ["mp4", "mov", "avi", "mkv"];
+
+fn has_video_extension(path: &Path) -> bool {
+    has_extension_in(path, &VIDEO_EXTENSIONS)
+}
+
+// Checks the container signature so a renamed non-video is not hashed as one
+fn is_video(path: &Path) -> bool {
+    let mut buffer = [0; 12];
+    let Ok(read) = File::open(path).and_then(|mut f| f.read(&mut buffer)) else {
+        return false;
+    };
+    let buffer = &buffer[..read];
+    // MP4/MOV: an ISO base media box such as `ftyp` at offset 4
+    let iso_box = buffer.len() >= 8
+        && [b"ftyp", b"moov", b"mdat", b"wide", b"free"]
+            .contains(&&buffer[4..8].try_into().unwrap());
+    // AVI: RIFF container with an AVI form type
+    let avi = buffer.len() >= 12 && buffer.starts_with(b"RIFF") && &buffer[8..12] == b"AVI ";
+    // MKV: EBML header
+    let mkv = buffer.starts_with(&[0x1A, 0x45, 0xDF, 0xA3]);
+    iso_box || avi || mkv
+}
+
+// Generated data that must never be scanned, regardless of configuration
+const ALWAYS_EXCLUDED_DIRS: [&str; 1] = [".cullrs"];
+
+// Skips generated directories, configured names and globs, and the duplicates
+// target under `root` so culled files are never rescanned
+fn is_excluded(
+    entry: &walkdir::DirEntry,
+    root: &Path,
+    config: &Config,
+    excluded_globs: &[glob::Pattern],
+) -> bool {
+    entry.path() == root.join(&config.duplicates_dir)
+        || entry.file_name().to_str().is_some_and(|name| {
+            ALWAYS_EXCLUDED_DIRS.contains(&name)
+                || config.excluded_dirs.iter().any(|excluded| name == excluded)
+        })
+        || entry.path().strip_prefix(root).is_ok_and(|relative| {
+            excluded_globs
+                .iter()
+                .any(|pattern| pattern.matches_path(relative))
+        })
+}
+
+// Like is_wanted_type, for files without an ImageFormat (videos)
+fn is_wanted_extension(path: &Path, config: &Config) -> bool {
+    config.file_types.is_empty()
+        || path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| config.file_types.contains(&ext.to_lowercase()))
+}
+
+// True when no file types are configured or the format is one of them
+fn is_wanted_type(format: ImageFormat, config: &Config) -> bool {
+    config.file_types.is_empty()
+        || format
+            .extensions_str()
+            .iter()
+            .any(|ext| config.file_types.iter().any(|wanted| wanted == ext))
+}
+
+/// Summary of a directory walk used to preview a scan before committing to it.
+struct ScanEstimate {
+    file_count: usize,
+    image_count: usize,
+    total_bytes: u64,
+    by_extension: BTreeMap<String, (usize, u64)>,
+    by_folder: BTreeMap<PathBuf, u64>,
+    sampled: usize,
+    hashing_time: Option<Duration>,
+}
+
+// Walks `dir` with the same rules as scan_directory, using file metadata and
+// headers only, then decodes a small evenly spaced sample of the images a scan
+// would hash to extrapolate the full hashing time.
+fn estimate_scan(dir: &Path) -> Result<ScanEstimate> {
+    let config = load_config(&get_config_path()?).unwrap_or_default();
+    let excluded_globs = compile_globs(&config.excluded_globs)?;
+
+    let mut estimate = ScanEstimate {
+        file_count: 0,
+        image_count: 0,
+        total_bytes: 0,
+        by_extension: BTreeMap::new(),
+        by_folder: BTreeMap::new(),
+        sampled: 0,
+        hashing_time: None,
+    };
+    let mut images = Vec::new();
+
+    for (entry, metadata) in walk_files(dir, &config, &excluded_globs) {
+        let size = metadata.len();
+        let ext = entry
+            .path()
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_else(|| "(none)".to_string());
+
+        estimate.file_count += 1;
+        estimate.total_bytes += size;
+        let ext_stats = estimate.by_extension.entry(ext).or_default();
+        ext_stats.0 += 1;
+        ext_stats.1 += size;
+        if let Some(parent) = entry.path().parent() {
+            *estimate.by_folder.entry(parent.to_path_buf()).or_default() += size;
         }
 
-        if file_count % 100 == 0 {
-            pb.set_message(format!(
-                "Scanned {} files, found {} images",
-                file_count,
-                images.len()
-            ));
+        if let FileKind::Image(format) =
+            classify_file(dir, entry.path(), size, &config, &ScanFilters::default())
+        {
+            images.push(DiscoveredImage {
+                path: entry.path().to_path_buf(),
+                format,
+            });
+        }
+    }
+    remove_paired_raws(&mut images);
+    estimate.image_count = images.len();
+
+    (estimate.hashing_time, estimate.sampled) = estimate_hashing_time(&images);
+
+    Ok(estimate)
+}
+
+// Decodes a small evenly spaced sample and extrapolates the time to hash all
+// `images` across the worker pool. Also returns how many samples decoded.
+fn estimate_hashing_time(images: &[DiscoveredImage]) -> (Option<Duration>, usize) {
+    const SAMPLE_SIZE: usize = 16;
+    if images.is_empty() {
+        return (None, 0);
+    }
+
+    let hasher = perceptual_hasher();
+    let step = images.len().div_ceil(SAMPLE_SIZE);
+    let start = Instant::now();
+    let sampled = images
+        .iter()
+        .step_by(step)
+        .filter(|image| hash_image_file(&hasher, image, None).is_ok())
+        .count();
+    if sampled == 0 {
+        return (None, 0);
+    }
+    let per_image = start.elapsed() / sampled as u32;
+    let threads = rayon::current_num_threads().max(1) as u32;
+    (Some(per_image * images.len() as u32 / threads), sampled)
+}
+
+// Reads dimensions from the image header only; unreadable headers are not filtered
+fn is_below_min_dimension(path: &Path, format: ImageFormat, min_dim: u32) -> bool {
+    let dimensions = File::open(path).ok().and_then(|f| {
+        ImageReader::with_format(BufReader::new(f), format)
+            .into_dimensions()
+            .ok()
+    });
+    match dimensions {
+        Some((width, height)) => width < min_dim || height < min_dim,
+        None => false,
+    }
+}
+
+/// Visually similar images found by `find_duplicates`. Once sorted by a
+/// selection strategy, `files[0]` is the keeper.
+#[derive(Debug)]
+struct DuplicateGroup {
+    files: Vec<PathBuf>,
+    /// Largest Hamming distance between the group's seed and another member
+    max_distance: u32,
+    /// How sure we are the members are true duplicates, from 0.0 to 1.0
+    confidence: f32,
+}
+
+impl DuplicateGroup {
+    // Bytes freed by removing every member except the keeper
+    fn reclaimable_bytes(&self) -> u64 {
+        self.files[1..]
+            .iter()
+            .map(|p| fs::metadata(p).map(|m| m.len()).unwrap_or(0))
+            .sum()
+    }
+}
+
+// Weighs hash closeness (relative to the threshold that admitted the furthest
+// member, which may be a per-format override) against metadata
+// agreement: the share of members with the same pixel dimensions as the seed.
+fn group_confidence(members: &[&DiscoveredImage], max_distance: u32, threshold: u32) -> f32 {
+    let closeness = 1.0 - (max_distance as f32 / threshold.saturating_add(1) as f32).min(1.0);
+
+    let dimensions = |image: &DiscoveredImage| {
+        File::open(&image.path).ok().and_then(|f| {
+            ImageReader::with_format(BufReader::new(f), image.format)
+                .into_dimensions()
+                .ok()
+        })
+    };
+    let seed = dimensions(members[0]);
+    let agreeing = members[1..]
+        .iter()
+        .filter(|image| seed.is_some() && dimensions(image) == seed)
+        .count();
+    let agreement = agreeing as f32 / (members.len() - 1) as f32;
+
+    0.7 * closeness + 0.3 * agreement
+}
+
+// Splits groups into those confident enough to resolve automatically and
+// those left for manual review
+fn partition_by_confidence(
+    groups: Vec<DuplicateGroup>,
+    min_confidence: Option<u8>,
+) -> (Vec<DuplicateGroup>, Vec<DuplicateGroup>) {
+    groups.into_iter().partition(|group| {
+        min_confidence.is_none_or(|min| group.confidence * 100.0 >= f32::from(min))
+    })
+}
+
+fn sort_groups(groups: &mut [DuplicateGroup], order: GroupOrder) {
+    match order {
+        GroupOrder::Bytes => {
+            groups.sort_by_cached_key(|g| std::cmp::Reverse(g.reclaimable_bytes()));
+        }
+        GroupOrder::Count => {
+            groups.sort_by_key(|g| std::cmp::Reverse(g.files.len()));
+        }
+        GroupOrder::Similarity => {
+            groups.sort_by_key(|g| g.max_distance);
+        }
+        GroupOrder::Path => {
+            groups.sort_by(|a, b| a.files[0].cmp(&b.files[0]));
         }
-        pb.tick();
     }
-
-    pb.finish_with_message(format!(
-        "Scan complete: {} images found from {} files",
-        images.len(),
-        file_count
-    ));
-    Ok(images)
 }
 
-fn find_duplicates(dir: &Path, threshold: u32) -> Result<Vec<Vec<PathBuf>>> {
-    let images = scan_directory(dir)?;
+fn find_duplicates(
+    dir: &Path,
+    threshold: u32,
+    format_thresholds: &BTreeMap<String, u32>,
+    filters: &ScanFilters,
+    force: bool,
+) -> Result<Vec<DuplicateGroup>> {
+    let Discovery {
+        images,
+        videos,
+        degenerate,
+    } = scan_directory(dir, filters, force)?;
+    if !degenerate.is_empty() {
+        eprintln!(
+            "▶ Set aside {} degenerate file(s) under {} (see `duplicates degenerate`)",
+            degenerate.len(),
+            format_bytes(DEGENERATE_FILE_SIZE)
+        );
+    }
+
+    // Videos have no perceptual hash, so they only group when byte-identical
+    let video_groups = exact_groups(&videos.iter().map(PathBuf::as_path).collect::<Vec<_>>())?;
+
+    let mut skip_list = DecodeSkipList::load(dir);
+    let (images, skipped): (Vec<_>, Vec<_>) = images
+        .into_iter()
+        .partition(|image| !skip_list.should_skip(dir, &image.path));
+    let skipped = skipped.len();
     if images.is_empty() {
-        return Ok(vec![]);
+        skip_list.report(dir, skipped);
+        return Ok(video_groups);
     }
 
-    println!("▶ Parallel hashing {} images…", images.len());
+    eprintln!("▶ Parallel hashing {} images…", images.len());
 
-    let hasher = HasherConfig::new()
-        .hash_alg(HashAlg::Gradient) // More robust than Mean for detecting similar images
-        .to_hasher();
+    let hasher = perceptual_hasher();
 
     let pb = ProgressBar::new(images.len() as u64);
     pb.set_style(ProgressStyle::with_template(
-        "{bar:40.cyan/blue} {pos:>7}/{len:7} {msg} [{elapsed_precise}]",
+        "{bar:40.cyan/blue} {pos:>7}/{len:7} {msg} ({per_sec}) [{elapsed_precise}]",
     )?);
     pb.set_message("Hashing images");
 
-    let hashes: Vec<(u64, PathBuf)> = benchmark("hashing all images", || {
+    let stats = IoStats::default();
+    let hashing_start = Instant::now();
+    let results: Vec<(Result<u64>, &DiscoveredImage)> = benchmark("hashing all images", || {
         images
             .par_iter()
-            .map(|path| -> Result<(u64, PathBuf)> {
-                let result = ImageReader::open(path)
-                    .with_context(|| format!("Failed to open {:?}", path))?
-                    .decode()
-                    .with_context(|| format!("Failed to decode {:?}", path))
-                    .map(|img| {
-                        let hash = hasher.hash_image(&img);
-                        (
-                            hash.as_bytes()
-                                .iter()
-                                .fold(0u64, |acc, &b| acc << 8 | b as u64),
-                            path.clone(),
-                        )
-                    });
+            .map(|image| {
+                let result = hash_image_file(&hasher, image, Some(&stats));
                 pb.inc(1);
-                result
+                (result, image)
             })
-            .collect::<Result<_>>()
-    })?;
+            .collect()
+    });
 
     // pb.finish();
     pb.finish_and_clear();
-    println!("▶ Hashing complete");
+    eprintln!("▶ Hashing complete");
+    eprintln!("▶ {}", stats.summary(hashing_start.elapsed()));
+
+    let mut hashes: Vec<(u64, &DiscoveredImage)> = Vec::new();
+    for (result, image) in results {
+        match result {
+            Ok(hash) => {
+                skip_list.remove(dir, &image.path);
+                hashes.push((hash, image));
+            }
+            Err(err) => skip_list.insert(dir, &image.path, format!("{:#}", err)),
+        }
+    }
+    // Only a cache: losing it just means failures are retried next time
+    if let Err(err) = skip_list.save(dir) {
+        eprintln!("⚠️  Could not remember decode failures: {:#}", err);
+    }
+    skip_list.report(dir, skipped);
 
     // Group similar hashes using Hamming distance
-    println!("▶ Grouping similar hashes with threshold {}", threshold);
+    if format_thresholds.is_empty() {
+        eprintln!("▶ Grouping similar hashes with threshold {}", threshold);
+    } else {
+        eprintln!(
+            "▶ Grouping similar hashes with threshold {} (overrides: {})",
+            threshold,
+            describe_format_thresholds(format_thresholds)
+        );
+    }
 
-    let mut groups: Vec<Vec<PathBuf>> = Vec::new();
+    let mut groups: Vec<DuplicateGroup> = Vec::new();
     let mut used = vec![false; hashes.len()];
 
+    // Query with the loosest threshold in play, then apply the per-pair one
+    let max_threshold = format_thresholds
+        .values()
+        .copied()
+        .fold(threshold, u32::max);
+    let index = SimilarityIndex::from_hashes(hashes.iter().map(|(hash, _)| *hash));
+
     for i in 0..hashes.len() {
         if used[i] {
             continue;
         }
 
-        let mut group = vec![hashes[i].1.clone()];
+        let mut group = vec![hashes[i].1];
+        let mut max_distance = 0;
+        // (distance, threshold) of the member closest to its own pair threshold
+        let mut furthest = (0, threshold);
         used[i] = true;
 
-        for j in (i + 1)..hashes.len() {
-            if used[j] {
+        for (j, distance) in index.query(hashes[i].0, max_threshold) {
+            if j <= i || used[j] {
                 continue;
             }
 
-            let distance = hamming_distance(hashes[i].0, hashes[j].0);
-            if distance <= threshold {
-                group.push(hashes[j].1.clone());
+            let pair_threshold = [hashes[i].1.format, hashes[j].1.format]
+                .iter()
+                .filter_map(|f| format_thresholds.get(format_key(*f)))
+                .min()
+                .copied()
+                .unwrap_or(threshold);
+            if distance <= pair_threshold {
+                group.push(hashes[j].1);
+                max_distance = max_distance.max(distance);
+                let ratio = |(d, t): (u32, u32)| d as f32 / t.saturating_add(1) as f32;
+                if ratio((distance, pair_threshold)) > ratio(furthest) {
+                    furthest = (distance, pair_threshold);
+                }
                 used[j] = true;
             }
         }
 
         if group.len() > 1 {
-            groups.push(group);
+            groups.push(DuplicateGroup {
+                confidence: group_confidence(&group, furthest.0, furthest.1),
+                files: group.iter().map(|image| image.path.clone()).collect(),
+                max_distance,
+            });
+        }
+    }
+    groups.extend(video_groups);
+
+    Ok(groups)
+}
+
+// Byte-identical groups when `exact`, otherwise perceptual grouping
+fn find_groups(
+    dir: &Path,
+    exact: bool,
+    verify: bool,
+    threshold: u32,
+    config: &Config,
+    filters: &ScanFilters,
+    force: bool,
+) -> Result<Vec<DuplicateGroup>> {
+    if exact {
+        find_exact_duplicates(dir, verify, filters, force)
+    } else {
+        find_duplicates(
+            dir,
+            threshold,
+            &config.duplicates_format_thresholds,
+            filters,
+            force,
+        )
+    }
+}
+
+// Groups byte-identical images and videos without decoding them. With
+// `verify`, members are also compared byte by byte and split off if they
+// differ after all.
+fn find_exact_duplicates(
+    dir: &Path,
+    verify: bool,
+    filters: &ScanFilters,
+    force: bool,
+) -> Result<Vec<DuplicateGroup>> {
+    let Discovery {
+        images,
+        videos,
+        degenerate,
+    } = scan_directory(dir, filters, force)?;
+    if !degenerate.is_empty() {
+        eprintln!(
+            "▶ Set aside {} degenerate file(s) under {} (see `duplicates degenerate`)",
+            degenerate.len(),
+            format_bytes(DEGENERATE_FILE_SIZE)
+        );
+    }
+
+    let paths: Vec<&Path> = images
+        .iter()
+        .map(|image| image.path.as_path())
+        .chain(videos.iter().map(PathBuf::as_path))
+        .collect();
+    let mut groups = exact_groups(&paths)?;
+
+    if verify {
+        let mut verified = Vec::new();
+        let mut mismatches = 0;
+        for group in groups {
+            let mut rest = group.files;
+            while rest.len() > 1 {
+                let first = rest.remove(0);
+                let (same, different): (Vec<_>, Vec<_>) = rest
+                    .into_par_iter()
+                    .map(|path| files_identical(&first, &path).map(|same| (path, same)))
+                    .collect::<Result<Vec<_>>>()?
+                    .into_iter()
+                    .partition(|(_, same)| *same);
+                mismatches += different.len();
+                rest = different.into_iter().map(|(path, _)| path).collect();
+                if !same.is_empty() {
+                    let mut files = vec![first];
+                    files.extend(same.into_iter().map(|(path, _)| path));
+                    verified.push(DuplicateGroup {
+                        files,
+                        max_distance: 0,
+                        confidence: 1.0,
+                    });
+                }
+            }
         }
+        eprintln!(
+            "▶ Verified {} group(s) byte by byte ({} hash mismatch(es))",
+            verified.len(),
+            mismatches
+        );
+        groups = verified;
     }
+    Ok(groups)
+}
 
+// Groups byte-identical files, keeping the input order within and across groups
+fn exact_groups(paths: &[&Path]) -> Result<Vec<DuplicateGroup>> {
+    let hashes = staged_content_hashes(paths)?;
+
+    let mut groups: Vec<DuplicateGroup> = Vec::new();
+    let mut by_hash: HashMap<blake3::Hash, usize> = HashMap::new();
+    for path in paths {
+        let Some(&hash) = hashes.get(path) else {
+            continue;
+        };
+        match by_hash.get(&hash) {
+            Some(&i) => groups[i].files.push(path.to_path_buf()),
+            None => {
+                by_hash.insert(hash, groups.len());
+                groups.push(DuplicateGroup {
+                    files: vec![path.to_path_buf()],
+                    max_distance: 0,
+                    confidence: 1.0,
+                });
+            }
+        }
+    }
+    groups.retain(|group| group.files.len() > 1);
     Ok(groups)
 }
 
+fn files_identical(a: &Path, b: &Path) -> Result<bool> {
+    let open = |p: &Path| {
+        File::open(p)
+            .map(|f| BufReader::with_capacity(64 * 1024, f))
+            .with_context(|| format!("Failed to open {:?}", p))
+    };
+    let (mut a_reader, mut b_reader) = (open(a)?, open(b)?);
+    loop {
+        let a_buf = a_reader.fill_buf()?;
+        let b_buf = b_reader.fill_buf()?;
+        let n = a_buf.len().min(b_buf.len());
+        if n == 0 {
+            return Ok(a_buf.len() == b_buf.len());
+        }
+        if a_buf[..n] != b_buf[..n] {
+            return Ok(false);
+        }
+        a_reader.consume(n);
+        b_reader.consume(n);
+    }
+}
+
+fn perceptual_hasher() -> Hasher {
+    HasherConfig::new()
+        .hash_alg(HashAlg::Gradient) // More robust than Mean for detecting similar images
+        .to_hasher()
+}
+
+// Decodes an image using its sniffed format and folds the perceptual hash into a u64
+// The file is read into memory first so disk time and decode time can be
+// measured separately when `stats` is given.
+fn hash_image_file(
+    hasher: &Hasher,
+    image: &DiscoveredImage,
+    stats: Option<&IoStats>,
+) -> Result<u64> {
+    let path = &image.path;
+    let read_start = Instant::now();
+    let bytes = fs::read(path).with_context(|| format!("Failed to open {:?}", path))?;
+    let read_time = read_start.elapsed();
+
+    let decode_start = Instant::now();
+    let hash = hash_image_bytes(hasher, &bytes, image.format, path)?;
+    if let Some(stats) = stats {
+        stats.record(bytes.len() as u64, read_time, decode_start.elapsed());
+    }
+    Ok(hash)
+}
+
+// Decodes in-memory image data and folds the perceptual hash into a u64;
+// `label` names the source in errors
+fn hash_image_bytes(
+    hasher: &Hasher,
+    bytes: &[u8],
+    format: ImageFormat,
+    label: &Path,
+) -> Result<u64> {
+    // A malformed file can panic inside a decoder; contain it to this file
+    // so the rest of the rayon batch keeps going.
+    let hash = panic::catch_unwind(AssertUnwindSafe(|| {
+        ImageReader::with_format(Cursor::new(bytes), format)
+            .decode()
+            .map(|img| hasher.hash_image(&img))
+    }))
+    .map_err(|payload| {
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown panic".to_string());
+        anyhow::anyhow!("Decoder panicked on {:?}: {}", label, message)
+    })?
+    .with_context(|| format!("Failed to decode {:?}", label))?;
+    Ok(hash
+        .as_bytes()
+        .iter()
+        .fold(0u64, |acc, &b| acc << 8 | b as u64))
+}
+
+/// Throughput counters shared by the hashing workers.
+#[derive(Default)]
+struct IoStats {
+    files: AtomicU64,
+    bytes: AtomicU64,
+    read_nanos: AtomicU64,
+    decode_nanos: AtomicU64,
+}
+
+impl IoStats {
+    fn record(&self, bytes: u64, read: Duration, decode: Duration) {
+        self.files.fetch_add(1, Ordering::Relaxed);
+        self.bytes.fetch_add(bytes, Ordering::Relaxed);
+        self.read_nanos
+            .fetch_add(read.as_nanos() as u64, Ordering::Relaxed);
+        self.decode_nanos
+            .fetch_add(decode.as_nanos() as u64, Ordering::Relaxed);
+    }
+
+    // Compares total time spent waiting on reads with time spent decoding
+    // across all workers to tell whether the disk or the CPU is the bottleneck.
+    fn summary(&self, elapsed: Duration) -> String {
+        let files = self.files.load(Ordering::Relaxed);
+        let bytes = self.bytes.load(Ordering::Relaxed);
+        let read = Duration::from_nanos(self.read_nanos.load(Ordering::Relaxed));
+        let decode = Duration::from_nanos(self.decode_nanos.load(Ordering::Relaxed));
+        let secs = elapsed.as_secs_f64().max(f64::EPSILON);
+        let bound = if read > decode {
+            "disk-bound"
+        } else {
+            "CPU-bound"
+        };
+        format!(
+            "{} read at {}/s, {:.1} files/s (read {:.2?} vs decode {:.2?} across workers: {})",
+            format_bytes(bytes),
+            format_bytes((bytes as f64 / secs) as u64),
+            files as f64 / secs,
+            read,
+            decode,
+            bound
+        )
+    }
+}
+
+fn content_hash(path: &Path) -> Result<blake3::Hash> {
+    let file = File::open(path).with_context(|| format!("Failed to open {:?}", path))?;
+    let mut hasher = blake3::Hasher::new();
+    hasher
+        .update_reader(BufReader::new(file))
+        .with_context(|| format!("Failed to read {:?}", path))?;
+    Ok(hasher.finalize())
+}
+
+// Bytes hashed from each end of a file before committing to a full hash
+const PARTIAL_HASH_BYTES: u64 = 64 * 1024;
+
+// Hashes the first and last PARTIAL_HASH_BYTES of a file. Files no larger
+// than both ends together are hashed whole, so the result is their full hash.
+fn partial_hash(path: &Path, size: u64) -> Result<blake3::Hash> {
+    if size <= 2 * PARTIAL_HASH_BYTES {
+        return content_hash(path);
+    }
+    let mut file = File::open(path).with_context(|| format!("Failed to open {:?}", path))?;
+    let mut buffer = vec![0; PARTIAL_HASH_BYTES as usize];
+    let mut hasher = blake3::Hasher::new();
+    file.read_exact(&mut buffer)
+        .and_then(|_| {
+            hasher.update(&buffer);
+            file.seek(SeekFrom::End(-(PARTIAL_HASH_BYTES as i64)))
+        })
+        .and_then(|_| file.read_exact(&mut buffer))
+        .with_context(|| format!("Failed to read {:?}", path))?;
+    hasher.update(&buffer);
+    Ok(hasher.finalize())
+}
+
+// Full content hashes for only the files that could have an identical twin:
+// those sharing a size, then sharing a partial hash. Images left out are
+// unique and absent from the returned map.
+fn staged_content_hashes<'a>(paths: &[&'a Path]) -> Result<HashMap<&'a Path, blake3::Hash>> {
+    let mut by_size: HashMap<u64, Vec<usize>> = HashMap::new();
+    for (i, path) in paths.iter().enumerate() {
+        let size = fs::metadata(path)
+            .with_context(|| format!("Failed to stat {:?}", path))?
+            .len();
+        by_size.entry(size).or_default().push(i);
+    }
+    let same_size: Vec<(usize, u64)> = by_size
+        .into_iter()
+        .filter(|(_, members)| members.len() > 1)
+        .flat_map(|(size, members)| members.into_iter().map(move |i| (i, size)))
+        .collect();
+
+    let partials: Vec<(usize, u64, blake3::Hash)> = same_size
+        .par_iter()
+        .map(|&(i, size)| Ok((i, size, partial_hash(paths[i], size)?)))
+        .collect::<Result<_>>()?;
+    let mut by_partial: HashMap<(u64, blake3::Hash), Vec<usize>> = HashMap::new();
+    for (i, size, hash) in &partials {
+        by_partial.entry((*size, *hash)).or_default().push(*i);
+    }
+    let same_partial: Vec<(usize, u64, blake3::Hash)> = partials
+        .iter()
+        .filter(|(_, size, hash)| by_partial[&(*size, *hash)].len() > 1)
+        .copied()
+        .collect();
+
+    let hashes: Vec<(usize, blake3::Hash)> = same_partial
+        .par_iter()
+        .map(|&(i, size, partial)| {
+            if size <= 2 * PARTIAL_HASH_BYTES {
+                Ok((i, partial))
+            } else {
+                Ok((i, content_hash(paths[i])?))
+            }
+        })
+        .collect::<Result<_>>()?;
+
+    eprintln!(
+        "▶ Exact matching: {} files, {} share a size, {} share a partial hash",
+        paths.len(),
+        same_size.len(),
+        same_partial.len()
+    );
+    Ok(hashes
+        .into_iter()
+        .map(|(i, hash)| (paths[i], hash))
+        .collect())
+}
+
+fn content_hash_all(images: &[DiscoveredImage]) -> Result<Vec<(blake3::Hash, &DiscoveredImage)>> {
+    let pb = ProgressBar::new(images.len() as u64);
+    pb.set_style(ProgressStyle::with_template(
+        "{bar:40.cyan/blue} {pos:>7}/{len:7} {msg} [{elapsed_precise}]",
+    )?);
+    pb.set_message("Hashing file contents");
+
+    let hashes = images
+        .par_iter()
+        .map(|image| {
+            let result = content_hash(&image.path).map(|hash| (hash, image));
+            pb.inc(1);
+            result
+        })
+        .collect::<Result<_>>();
+    pb.finish_and_clear();
+    hashes
+}
+
+/// Files that failed to decode, persisted per scanned directory under the
+/// user cache directory (never inside the scanned tree, which may be
+/// read-only) so they are not retried on every pass while they stay unchanged.
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct DecodeSkipList {
+    /// Keyed by path relative to the scanned directory
+    entries: BTreeMap<PathBuf, DecodeFailure>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct DecodeFailure {
+    size: u64,
+    modified: u64,
+    reason: String,
+}
+
+impl DecodeSkipList {
+    // e.g. ~/.cache/cullrs/decode-failures/<hash of the canonical dir>.json
+    fn store_path(dir: &Path) -> Option<PathBuf> {
+        let root = fs::canonicalize(dir).ok()?;
+        let key = blake3::hash(root.as_os_str().as_encoded_bytes()).to_hex();
+        Some(
+            dirs::cache_dir()?
+                .join("cullrs")
+                .join("decode-failures")
+                .join(format!("{}.json", &key[..16])),
+        )
+    }
+
+    fn load(dir: &Path) -> Self {
+        Self::store_path(dir)
+            .and_then(|file| fs::read_to_string(file).ok())
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&mut self, dir: &Path) -> Result<()> {
+        let file = Self::store_path(dir)
+            .ok_or_else(|| anyhow::anyhow!("Could not find a cache directory"))?;
+        self.entries.retain(|path, _| dir.join(path).is_file());
+        if self.entries.is_empty() {
+            if file.exists() {
+                fs::remove_file(&file).with_context(|| format!("Failed to remove {:?}", file))?;
+            }
+            return Ok(());
+        }
+        if let Some(parent) = file.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create directory {:?}", parent))?;
+        }
+        fs::write(&file, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write {:?}", file))
+    }
+
+    fn key(dir: &Path, path: &Path) -> PathBuf {
+        path.strip_prefix(dir).unwrap_or(path).to_path_buf()
+    }
+
+    // Size and modification time; a change to either means the file is retried
+    fn fingerprint(path: &Path) -> Option<(u64, u64)> {
+        let metadata = fs::metadata(path).ok()?;
+        let modified = metadata
+            .modified()
+            .ok()?
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .ok()?
+            .as_secs();
+        Some((metadata.len(), modified))
+    }
+
+    fn should_skip(&self, dir: &Path, path: &Path) -> bool {
+        match self.entries.get(&Self::key(dir, path)) {
+            Some(failure) => Self::fingerprint(path) == Some((failure.size, failure.modified)),
+            None => false,
+        }
+    }
+
+    fn insert(&mut self, dir: &Path, path: &Path, reason: String) {
+        if let Some((size, modified)) = Self::fingerprint(path) {
+            self.entries.insert(
+                Self::key(dir, path),
+                DecodeFailure {
+                    size,
+                    modified,
+                    reason,
+                },
+            );
+        }
+    }
+
+    fn remove(&mut self, dir: &Path, path: &Path) {
+        self.entries.remove(&Self::key(dir, path));
+    }
+
+    // Per-extension summary of every file this directory cannot decode
+    fn report(&self, dir: &Path, previously_skipped: usize) {
+        if self.entries.is_empty() {
+            return;
+        }
+        let mut by_extension: BTreeMap<String, (usize, &str)> = BTreeMap::new();
+        for (path, failure) in &self.entries {
+            let ext = path
+                .extension()
+                .map(|e| e.to_string_lossy().to_lowercase())
+                .unwrap_or_else(|| "(none)".to_string());
+            by_extension.entry(ext).or_insert((0, &failure.reason)).0 += 1;
+        }
+
+        eprintln!(
+            "⚠️  {} file(s) could not be decoded and were left out:",
+            self.entries.len()
+        );
+        for (ext, (count, reason)) in by_extension {
+            eprintln!("   {} ×{}: {}", ext, count, reason);
+        }
+        if previously_skipped > 0
+            && let Some(file) = Self::store_path(dir)
+        {
+            eprintln!(
+                "   {} of these failed before and were not retried; delete {} to retry them",
+                previously_skipped,
+                file.display()
+            );
+        }
+    }
+}
+
 fn hamming_distance(hash1: u64, hash2: u64) -> u32 {
     (hash1 ^ hash2).count_ones()
 }
 
-fn sort_group_by_strategy(group: &mut Vec<PathBuf>, strategy: &SelectionStrategy) {
+// Canonical config key for a format, e.g. "jpg" for both .jpg and .jpeg
+fn format_key(format: ImageFormat) -> &'static str {
+    format
+        .extensions_str()
+        .first()
+        .copied()
+        .unwrap_or("unknown")
+}
+
+fn parse_format_threshold(s: &str) -> Result<(String, u32)> {
+    let (format, threshold) = s
+        .split_once('=')
+        .ok_or_else(|| anyhow::anyhow!("Expected FORMAT=N, got '{}'", s))?;
+    let format = ImageFormat::from_extension(format.trim())
+        .ok_or_else(|| anyhow::anyhow!("Unknown image format '{}'", format))?;
+    let threshold: u32 = threshold
+        .trim()
+        .parse()
+        .with_context(|| format!("Invalid threshold in '{}'", s))?;
+    if threshold > 64 {
+        anyhow::bail!("Threshold must be between 0 and 64");
+    }
+    Ok((format_key(format).to_string(), threshold))
+}
+
+fn describe_format_thresholds(format_thresholds: &BTreeMap<String, u32>) -> String {
+    if format_thresholds.is_empty() {
+        return "none".to_string();
+    }
+    format_thresholds
+        .iter()
+        .map(|(format, t)| format!("{}={}", format, t))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn sort_group_by_strategy(group: &mut [PathBuf], strategy: &SelectionStrategy) {
     match strategy {
         SelectionStrategy::Oldest => {
-            group.sort_by_key(|p| get_timestamp(p));
+            group.sort_by_cached_key(|p| get_timestamp(p).0);
         }
         SelectionStrategy::Newest => {
-            group.sort_by_key(|p| std::cmp::Reverse(get_timestamp(p)));
+            group.sort_by_cached_key(|p| std::cmp::Reverse(get_timestamp(p).0));
         }
         SelectionStrategy::Largest => {
             group.sort_by_key(|p| std::cmp::Reverse(fs::metadata(p).map(|m| m.len()).unwrap_or(0)));
@@ -719,35 +3641,93 @@ fn sort_group_by_strategy(group: &mut Vec<PathBuf>, strategy: &SelectionStrategy
         SelectionStrategy::Smallest => {
             group.sort_by_key(|p| fs::metadata(p).map(|m| m.len()).unwrap_or(u64::MAX));
         }
+        SelectionStrategy::Original => {
+            group.sort_by_cached_key(|p| {
+                (
+                    std::cmp::Reverse(filename_originality(p)),
+                    get_timestamp(p).0,
+                )
+            });
+        }
+    }
+    // Whatever the strategy, keep a JPEG that has a RAW pair: culling it
+    // would take the shot's RAW along with it
+    if let Some(i) = group.iter().position(|p| !raw_companions(p).is_empty()) {
+        group[..=i].rotate_right(1);
     }
 }
 
-fn get_unique_destination(target_dir: &Path, source: &Path) -> Result<PathBuf> {
-    let file_name = source.file_name().unwrap();
-    let mut dest = target_dir.join(file_name);
+// Higher is more likely the camera's original file: camera-style names
+// (IMG_1234, DSC0001, PXL_20240101_123456) score up, copy markers down.
+fn filename_originality(path: &Path) -> i32 {
+    const CAMERA_PREFIXES: [&str; 11] = [
+        "IMG_", "IMG", "DSC_", "_DSC", "DSCF", "DSCN", "DSC", "DJI_", "PXL_", "GOPR", "MVI_",
+    ];
+    const COPY_MARKERS: [&str; 4] = ["copy", "edited", "duplicate", "export"];
+
+    let Some(stem) = path.file_stem().map(|s| s.to_string_lossy()) else {
+        return 0;
+    };
+    let upper = stem.to_uppercase();
+    let lower = stem.to_lowercase();
+    let is_counter = |rest: &str| {
+        rest.chars().next().is_some_and(|c| c.is_ascii_digit())
+            && rest.chars().all(|c| c.is_ascii_digit() || c == '_')
+    };
 
-    if !dest.exists() {
-        return Ok(dest);
+    let mut score = 0;
+    if CAMERA_PREFIXES
+        .iter()
+        .any(|prefix| upper.strip_prefix(prefix).is_some_and(is_counter))
+        || is_counter(&upper)
+    {
+        score += 2;
+    }
+    if COPY_MARKERS.iter().any(|marker| lower.contains(marker)) {
+        score -= 2;
     }
+    // "name (1)" style suffixes added by browsers and file managers
+    if let Some(open) = lower.rfind(" (")
+        && lower.ends_with(')')
+        && lower[open + 2..lower.len() - 1]
+            .chars()
+            .all(|c| c.is_ascii_digit())
+    {
+        score -= 2;
+    }
+    score
+}
 
+// Reserves a free name in `target_dir` by atomically creating an empty
+// placeholder (O_EXCL), so concurrent moves or other processes can never be
+// handed the same destination. The caller renames over the placeholder.
+fn get_unique_destination(target_dir: &Path, source: &Path) -> Result<PathBuf> {
+    let file_name = source.file_name().unwrap();
     let stem = source.file_stem().unwrap().to_string_lossy();
     let ext = source
         .extension()
         .map(|e| format!(".{}", e.to_string_lossy()))
         .unwrap_or_default();
 
-    let mut counter = 1;
-    loop {
-        dest = target_dir.join(format!("{}_{}{}", stem, counter, ext));
-        if !dest.exists() {
-            return Ok(dest);
-        }
-        counter += 1;
-
-        if counter > 9999 {
-            anyhow::bail!("Too many files with similar names in target directory");
+    for counter in 0..=9999 {
+        let dest = if counter == 0 {
+            target_dir.join(file_name)
+        } else {
+            target_dir.join(format!("{}_{}{}", stem, counter, ext))
+        };
+        match fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&dest)
+        {
+            Ok(_) => return Ok(dest),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to reserve {}", dest.display()));
+            }
         }
     }
+    anyhow::bail!("Too many files with similar names in target directory");
 }
 
 fn validate_directory(path: &Path) -> Result<()> {
@@ -760,16 +3740,108 @@ fn validate_directory(path: &Path) -> Result<()> {
     Ok(())
 }
 
-fn validate_target_directory(source: &Path, target: &Path) -> Result<()> {
+fn validate_target_directory(source: &Path, target: &Path, config: &Config) -> Result<()> {
     if target == source {
         anyhow::bail!("Target directory cannot be the same as source directory");
     }
-    if target.starts_with(source) && target != source.join("duplicates") {
-        anyhow::bail!("Target directory cannot be a subdirectory of source (except 'duplicates')");
+    if target.starts_with(source) && target != source.join(&config.duplicates_dir) {
+        anyhow::bail!(
+            "Target directory cannot be a subdirectory of source (except the configured '{}')",
+            config.duplicates_dir.display()
+        );
+    }
+    Ok(())
+}
+
+// A relative duplicates directory must resolve to a real subdirectory, never
+// the scanned directory itself or somewhere above it
+fn validate_duplicates_dir(dir: &Path) -> Result<()> {
+    if dir.is_absolute() {
+        return Ok(());
+    }
+    let has_normal = dir
+        .components()
+        .any(|c| matches!(c, std::path::Component::Normal(_)));
+    let escapes = dir
+        .components()
+        .any(|c| matches!(c, std::path::Component::ParentDir));
+    if !has_normal || escapes {
+        anyhow::bail!(
+            "Duplicates directory '{}' must be a subdirectory of the scanned directory or an absolute path",
+            dir.display()
+        );
     }
     Ok(())
 }
 
+// Asks which file to keep for each group, suggesting the strategy's keeper.
+// Returns keeper-first groups for the ones resolved; skipped groups are left
+// out, and `q` (or end of input) stops the review with what was decided so far.
+fn review_groups(groups: &[DuplicateGroup]) -> Result<Vec<Vec<PathBuf>>> {
+    let mut decisions = Vec::new();
+    'groups: for (i, group) in groups.iter().enumerate() {
+        println!(
+            "\n✨ Group {}/{} ({} files, max distance {}, {:.0}% confidence):",
+            i + 1,
+            groups.len(),
+            group.files.len(),
+            group.max_distance,
+            group.confidence * 100.0
+        );
+        for (n, file) in group.files.iter().enumerate() {
+            let size = fs::metadata(file).map(|m| m.len()).unwrap_or(0);
+            let dimensions = ImageReader::open(file)
+                .and_then(|reader| reader.with_guessed_format())
+                .ok()
+                .and_then(|reader| reader.into_dimensions().ok())
+                .map(|(w, h)| format!("{}x{}", w, h))
+                .unwrap_or_else(|| "?x?".to_string());
+            println!(
+                "   [{}] {}{}",
+                n + 1,
+                file.display(),
+                if n == 0 { "  (suggested)" } else { "" }
+            );
+            println!(
+                "       {}, {}, {}",
+                format_bytes(size),
+                dimensions,
+                describe_timestamp(file)
+            );
+        }
+
+        loop {
+            print!(
+                "Keep which file? [1-{}, Enter = 1, s = skip group, q = finish review]: ",
+                group.files.len()
+            );
+            io::stdout().flush()?;
+            let mut input = String::new();
+            if io::stdin().read_line(&mut input)? == 0 {
+                break 'groups;
+            }
+            match input.trim().to_lowercase().as_str() {
+                "" => {
+                    decisions.push(group.files.clone());
+                    break;
+                }
+                "s" => break,
+                "q" => break 'groups,
+                choice => match choice.parse::<usize>() {
+                    Ok(n) if (1..=group.files.len()).contains(&n) => {
+                        let mut files = group.files.clone();
+                        files.swap(0, n - 1);
+                        decisions.push(files);
+                        break;
+                    }
+                    _ => println!("   Please enter a number from the list, s or q."),
+                },
+            }
+        }
+    }
+    Ok(decisions)
+}
+
 fn confirm_action(message: &str) -> Result<bool> {
     print!("{} [y/N]: ", message);
     io::stdout().flush()?;
@@ -780,18 +3852,41 @@ fn confirm_action(message: &str) -> Result<bool> {
     Ok(input.trim().to_lowercase() == "y" || input.trim().to_lowercase() == "yes")
 }
 
+// Set from the global `--config` flag
+static CONFIG_PATH_OVERRIDE: OnceLock<PathBuf> = OnceLock::new();
+
+// The `--config` file if given, otherwise ~/.config/cullrs/config.toml. A
+// config.json left by older versions is converted to TOML on first use.
 fn get_config_path() -> Result<PathBuf> {
+    if let Some(path) = CONFIG_PATH_OVERRIDE.get() {
+        return Ok(path.clone());
+    }
     let home = dirs::home_dir().ok_or_else(|| anyhow::anyhow!("Could not find home directory"))?;
-    Ok(home.join(".config").join("cullrs").join("config.json"))
+    let dir = home.join(".config").join("cullrs");
+    let path = dir.join("config.toml");
+    let legacy = dir.join("config.json");
+    if !path.exists() && legacy.exists() {
+        let config = load_config(&legacy)
+            .with_context(|| format!("Failed to migrate config {:?}", legacy))?;
+        save_config(&path, &config)?;
+        fs::rename(&legacy, dir.join("config.json.bak"))?;
+        eprintln!("▶ Migrated {} to {}", legacy.display(), path.display());
+    }
+    Ok(path)
 }
 
+// JSON for `.json` files (older versions), TOML otherwise
 fn load_config(path: &Path) -> Result<Config> {
     if !path.exists() {
         return Ok(Config::default());
     }
 
     let content = fs::read_to_string(path)?;
-    let config: Config = serde_json::from_str(&content)?;
+    let config: Config = if path.extension().is_some_and(|ext| ext == "json") {
+        serde_json::from_str(&content)?
+    } else {
+        toml::from_str(&content)?
+    };
     Ok(config)
 }
 
@@ -800,20 +3895,262 @@ fn save_config(path: &Path, config: &Config) -> Result<()> {
         fs::create_dir_all(parent)?;
     }
 
-    let content = serde_json::to_string_pretty(config)?;
+    let content = if path.extension().is_some_and(|ext| ext == "json") {
+        serde_json::to_string_pretty(config)?
+    } else {
+        toml::to_string_pretty(config)?
+    };
     fs::write(path, content)?;
     Ok(())
 }
 
+fn compile_globs(patterns: &[String]) -> Result<Vec<glob::Pattern>> {
+    patterns
+        .iter()
+        .map(|p| glob::Pattern::new(p).with_context(|| format!("Invalid glob pattern '{}'", p)))
+        .collect()
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if secs < 60 {
+        format!("{}s", secs.max(1))
+    } else if secs < 3600 {
+        format!("{} min", secs.div_ceil(60))
+    } else {
+        format!("{}h {}min", secs / 3600, (secs % 3600) / 60)
+    }
+}
+
 fn benchmark<T, F: FnOnce() -> T>(label: &str, f: F) -> T {
     let start = Instant::now();
     let result = f();
-    println!("⏱ {} took {:.2?}", label, start.elapsed());
+    eprintln!("⏱ {} took {:.2?}", label, start.elapsed());
     result
 }
 
-fn get_timestamp(path: &PathBuf) -> SystemTime {
-    fs::metadata(path)
-        .and_then(|m| m.created())
-        .unwrap_or(SystemTime::UNIX_EPOCH)
+/// Where a file's timestamp came from, in fallback order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TimestampSource {
+    /// Filesystem birth time (statx on Linux)
+    Birth,
+    /// EXIF DateTimeOriginal
+    Exif,
+    /// Last modification time
+    Modified,
+    /// Nothing was readable
+    Unknown,
+}
+
+impl TimestampSource {
+    fn label(&self) -> &'static str {
+        match self {
+            TimestampSource::Birth => "birth time",
+            TimestampSource::Exif => "EXIF date",
+            TimestampSource::Modified => "modified time",
+            TimestampSource::Unknown => "unknown",
+        }
+    }
+}
+
+// Birth time → EXIF date → mtime. std's `created()` uses statx on Linux and
+// errors when the filesystem does not record a birth time.
+fn get_timestamp(path: &Path) -> (SystemTime, TimestampSource) {
+    let metadata = fs::metadata(path).ok();
+    if let Some(created) = metadata.as_ref().and_then(|m| m.created().ok()) {
+        return (created, TimestampSource::Birth);
+    }
+    if let Some(taken) = read_exif_date(path) {
+        return (taken, TimestampSource::Exif);
+    }
+    if let Some(modified) = metadata.as_ref().and_then(|m| m.modified().ok()) {
+        return (modified, TimestampSource::Modified);
+    }
+    (SystemTime::UNIX_EPOCH, TimestampSource::Unknown)
+}
+
+// EXIF dates carry no timezone, so they are interpreted as local time
+fn read_exif_date(path: &Path) -> Option<SystemTime> {
+    let file = File::open(path).ok()?;
+    let exif = exif::Reader::new()
+        .read_from_container(&mut BufReader::new(file))
+        .ok()?;
+    let field = exif.get_field(exif::Tag::DateTimeOriginal, exif::In::PRIMARY)?;
+    let exif::Value::Ascii(ref values) = field.value else {
+        return None;
+    };
+    let dt = exif::DateTime::from_ascii(values.first()?).ok()?;
+    let naive = NaiveDate::from_ymd_opt(dt.year.into(), dt.month.into(), dt.day.into())?
+        .and_hms_opt(dt.hour.into(), dt.minute.into(), dt.second.into())?;
+    let local = Local.from_local_datetime(&naive).earliest()?;
+    Some(local.into())
+}
+
+fn describe_timestamp(path: &Path) -> String {
+    let (time, source) = get_timestamp(path);
+    if source == TimestampSource::Unknown {
+        return "no readable timestamp".to_string();
+    }
+    format!(
+        "{} ({})",
+        DateTime::<Local>::from(time).format("%Y-%m-%d %H:%M:%S"),
+        source.label()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A fresh directory per test under the system temp dir
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("cullrs-test-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn touch(dir: &Path, name: &str) -> PathBuf {
+        let path = dir.join(name);
+        fs::write(&path, name).unwrap();
+        path
+    }
+
+    fn plan(root: &Path, groups: Vec<PlanGroup>) -> CullPlan {
+        CullPlan {
+            root: root.to_path_buf(),
+            threshold: 0,
+            strategy: SelectionStrategy::Oldest,
+            groups,
+        }
+    }
+
+    fn group(id: usize, keep: &Path, cull: &[&Path]) -> PlanGroup {
+        PlanGroup {
+            id,
+            confidence: 1.0,
+            max_distance: 0,
+            keep: keep.to_path_buf(),
+            cull: cull.iter().map(|p| p.to_path_buf()).collect(),
+            raw_pairs: Vec::new(),
+            files: Vec::new(),
+        }
+    }
+
+    fn validation_error(plan: &CullPlan) -> String {
+        validate_plan(plan).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn validate_plan_rejects_culling_a_keeper() {
+        let dir = temp_dir("cull-keeper");
+        let (a, b, c) = (touch(&dir, "a"), touch(&dir, "b"), touch(&dir, "c"));
+        let plan = plan(&dir, vec![group(1, &a, &[&b]), group(2, &c, &[&a])]);
+        assert!(validation_error(&plan).contains("which is a keeper"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn validate_plan_rejects_culling_a_path_twice() {
+        let dir = temp_dir("cull-twice");
+        let (a, b, c) = (touch(&dir, "a"), touch(&dir, "b"), touch(&dir, "c"));
+
+        let across = plan(&dir, vec![group(1, &a, &[&c]), group(2, &b, &[&c])]);
+        assert!(validation_error(&across).contains("which group 1 already culls"));
+
+        let within = plan(&dir, vec![group(1, &a, &[&c, &c])]);
+        assert!(validation_error(&within).contains("which group 1 already culls"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn validate_plan_rejects_raw_pair_of_a_kept_jpeg() {
+        let dir = temp_dir("raw-pair");
+        let (a, b) = (touch(&dir, "a.jpg"), touch(&dir, "b.jpg"));
+        let raw = touch(&dir, "a.cr2");
+        let mut g = group(1, &a, &[&b]);
+        g.raw_pairs.push(RawPair {
+            jpeg: a.clone(),
+            raw,
+        });
+        assert!(validation_error(&plan(&dir, vec![g])).contains("which it does not cull"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn validate_plan_keeps_raw_pairs_of_culled_jpegs() {
+        let dir = temp_dir("raw-pair-ok");
+        let (a, b) = (touch(&dir, "a.jpg"), touch(&dir, "b.jpg"));
+        let raw = touch(&dir, "b.cr2");
+        let mut g = group(1, &a, &[&b]);
+        g.raw_pairs.push(RawPair {
+            jpeg: b.clone(),
+            raw: raw.clone(),
+        });
+        let (groups, raw_pairs) = validate_plan(&plan(&dir, vec![g])).unwrap();
+        assert_eq!(groups, vec![vec![a, b.clone()]]);
+        assert_eq!(raw_pairs.get(&b), Some(&vec![raw]));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn validate_plan_skips_group_with_missing_keeper() {
+        let dir = temp_dir("missing-keeper");
+        let (b, d) = (touch(&dir, "b"), touch(&dir, "d"));
+        let c = touch(&dir, "c");
+        let missing = dir.join("gone");
+        let plan = plan(&dir, vec![group(1, &missing, &[&b]), group(2, &c, &[&d])]);
+        let (groups, _) = validate_plan(&plan).unwrap();
+        assert_eq!(groups, vec![vec![c, d]]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn validate_plan_skips_missing_culled_file() {
+        let dir = temp_dir("missing-cull");
+        let (a, b) = (touch(&dir, "a"), touch(&dir, "b"));
+        let missing = dir.join("gone");
+        let partly_missing = plan(&dir, vec![group(1, &a, &[&missing, &b])]);
+        let (groups, _) = validate_plan(&partly_missing).unwrap();
+        assert_eq!(groups, vec![vec![a.clone(), b]]);
+
+        // A group left with only its keeper is dropped entirely
+        let only_missing = plan(&dir, vec![group(1, &a, &[&missing])]);
+        assert!(validate_plan(&only_missing).unwrap().0.is_empty());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn parse_size_accepts_binary_units() {
+        assert_eq!(parse_size("1024"), Ok(1024));
+        assert_eq!(parse_size("500k"), Ok(500 * 1024));
+        assert_eq!(parse_size("2MB"), Ok(2 << 20));
+        assert_eq!(parse_size("1.5GiB"), Ok(3 << 29));
+        assert_eq!(parse_size(" 10 kb "), Ok(10 * 1024));
+        assert_eq!(parse_size("1T"), Ok(1 << 40));
+    }
+
+    #[test]
+    fn parse_size_rejects_garbage() {
+        assert!(parse_size("").is_err());
+        assert!(parse_size("MB").is_err());
+        assert!(parse_size("-1").is_err());
+        assert!(parse_size("1.2.3").is_err());
+        assert!(parse_size("5 parsecs").is_err());
+    }
 }
@@ -0,0 +1,145 @@
+use crate::hamming_distance;
+
+// BK-tree over 64-bit perceptual hashes keyed by Hamming distance. Range
+// queries only descend into children whose edge distance is within `max` of
+// the query's distance to the node (triangle inequality), which avoids
+// comparing every pair on large libraries.
+#[derive(Default)]
+pub struct SimilarityIndex {
+    nodes: Vec<Node>,
+}
+
+struct Node {
+    hash: u64,
+    item: usize,
+    // (distance to this node, index into `nodes`)
+    children: Vec<(u32, usize)>,
+}
+
+impl SimilarityIndex {
+    // Builds an index where each hash's item is its position in `hashes`
+    pub fn from_hashes(hashes: impl IntoIterator<Item = u64>) -> Self {
+        let mut index = Self::default();
+        for (item, hash) in hashes.into_iter().enumerate() {
+            index.insert(hash, item);
+        }
+        index
+    }
+
+    pub fn insert(&mut self, hash: u64, item: usize) {
+        let new = self.nodes.len();
+        self.nodes.push(Node {
+            hash,
+            item,
+            children: Vec::new(),
+        });
+        if new == 0 {
+            return;
+        }
+
+        let mut current = 0;
+        loop {
+            let distance = hamming_distance(self.nodes[current].hash, hash);
+            match self.nodes[current]
+                .children
+                .iter()
+                .find(|(d, _)| *d == distance)
+            {
+                Some(&(_, child)) => current = child,
+                None => {
+                    self.nodes[current].children.push((distance, new));
+                    return;
+                }
+            }
+        }
+    }
+
+    // Every item within `max` bits of `hash`, with its distance, ordered by item
+    pub fn query(&self, hash: u64, max: u32) -> Vec<(usize, u32)> {
+        let mut matches = Vec::new();
+        if self.nodes.is_empty() {
+            return matches;
+        }
+
+        let mut stack = vec![0];
+        while let Some(current) = stack.pop() {
+            let node = &self.nodes[current];
+            let distance = hamming_distance(node.hash, hash);
+            if distance <= max {
+                matches.push((node.item, distance));
+            }
+            stack.extend(
+                node.children
+                    .iter()
+                    .filter(|(d, _)| d.abs_diff(distance) <= max)
+                    .map(|(_, child)| *child),
+            );
+        }
+        matches.sort_unstable();
+        matches
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // xorshift64*, so the test needs no extra dependencies and is reproducible
+    fn random_hashes(seed: u64, count: usize) -> Vec<u64> {
+        let mut state = seed;
+        (0..count)
+            .map(|_| {
+                state ^= state >> 12;
+                state ^= state << 25;
+                state ^= state >> 27;
+                state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+            })
+            .collect()
+    }
+
+    fn brute_force(hashes: &[u64], hash: u64, max: u32) -> Vec<(usize, u32)> {
+        hashes
+            .iter()
+            .enumerate()
+            .map(|(item, &other)| (item, hamming_distance(hash, other)))
+            .filter(|&(_, distance)| distance <= max)
+            .collect()
+    }
+
+    #[test]
+    fn query_matches_brute_force() {
+        let mut hashes = random_hashes(0x9E37_79B9_7F4A_7C15, 500);
+        // Near neighbours and exact duplicates, which share a BK-tree edge
+        let base = hashes[0];
+        hashes.extend([base, base, base ^ 1, base ^ 0b110, hashes[7]]);
+        let index = SimilarityIndex::from_hashes(hashes.iter().copied());
+
+        for max in [0, 1, 5, 12, 20, 32, 64] {
+            for &hash in hashes.iter().take(50).chain(&random_hashes(42, 20)) {
+                assert_eq!(
+                    index.query(hash, max),
+                    brute_force(&hashes, hash, max),
+                    "hash {hash:#018x}, max {max}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn exact_duplicates_are_all_returned() {
+        let index = SimilarityIndex::from_hashes([7, 7, 7, 8]);
+        assert_eq!(index.query(7, 0), vec![(0, 0), (1, 0), (2, 0)]);
+    }
+
+    #[test]
+    fn max_64_returns_everything() {
+        let hashes = random_hashes(1, 100);
+        let index = SimilarityIndex::from_hashes(hashes.iter().copied());
+        assert_eq!(index.query(0, 64).len(), hashes.len());
+    }
+
+    #[test]
+    fn empty_index_has_no_matches() {
+        assert!(SimilarityIndex::default().query(0, 64).is_empty());
+    }
+}